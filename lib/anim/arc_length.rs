@@ -0,0 +1,184 @@
+use nalgebra::{UnitQuaternion, Vector2, Vector3, Vector4, norm};
+
+use anim::{AnimParam, Cont, Interpolate, Sampler, Time};
+
+/// Types we can measure a distance between.
+pub trait Metric {
+  fn distance(a: &Self, b: &Self) -> f32;
+}
+
+impl Metric for f32 {
+  fn distance(a: &Self, b: &Self) -> f32 {
+    (b - a).abs()
+  }
+}
+
+impl Metric for Vector2<f32> {
+  fn distance(a: &Self, b: &Self) -> f32 {
+    norm(&(*b - *a))
+  }
+}
+
+impl Metric for Vector3<f32> {
+  fn distance(a: &Self, b: &Self) -> f32 {
+    norm(&(*b - *a))
+  }
+}
+
+impl Metric for Vector4<f32> {
+  fn distance(a: &Self, b: &Self) -> f32 {
+    norm(&(*b - *a))
+  }
+}
+
+/// The distance between two orientations is the angle, in radians, of the rotation going from one
+/// to the other.
+impl Metric for UnitQuaternion<f32> {
+  fn distance(a: &Self, b: &Self) -> f32 {
+    let (qa, qb) = (a.quaternion(), b.quaternion());
+    // q and -q are the same orientation
+    let dot = (qa.w * qb.w + qa.i * qb.i + qa.j * qb.j + qa.k * qb.k).abs().min(1.);
+
+    2. * dot.acos()
+  }
+}
+
+/// Arc-length lookup table of an animation parameter between two times.
+///
+/// The curve is approximated by a polyline of `steps` segments, uniformly spread in time. The
+/// table maps distances traveled along that polyline back to times, which is what you need to
+/// move along a path at constant speed.
+#[derive(Clone, Debug)]
+pub struct ArcLength {
+  // (time, cumulative distance) pairs, sorted by both
+  table: Vec<(Time, f32)>
+}
+
+impl ArcLength {
+  /// Build the table of `param` between `t0` and `t1`. `t1` can be the time of the last key of
+  /// `param`.
+  ///
+  /// Returns `None` if `param` cannot be sampled in that range.
+  pub fn new<T>(param: &AnimParam<T>, t0: Time, t1: Time, steps: usize) -> Option<Self> where T: Interpolate + Metric {
+    let steps = if steps == 0 { 1 } else { steps };
+    let mut sampler = Sampler::new();
+    let mut table = Vec::with_capacity(steps + 1);
+    let mut prev = None;
+    let mut dist = 0.;
+
+    for i in 0..steps + 1 {
+      let t = t0 + (t1 - t0) * i as Time / steps as Time;
+      let value = match sample_clamped(&mut sampler, param, t) {
+        Some(value) => value,
+        None => return None
+      };
+
+      if let Some(prev) = prev {
+        dist += Metric::distance(&prev, &value);
+      }
+
+      table.push((t, dist));
+      prev = Some(value);
+    }
+
+    Some(ArcLength {
+      table: table
+    })
+  }
+
+  /// Total length of the path.
+  pub fn length(&self) -> f32 {
+    self.table[self.table.len() - 1].1
+  }
+
+  /// Time range the table was built for.
+  pub fn time_range(&self) -> (Time, Time) {
+    (self.table[0].0, self.table[self.table.len() - 1].0)
+  }
+
+  /// Distance traveled along the path at time `t`. Clamped to the range of the table.
+  pub fn distance_at_time(&self, t: Time) -> f32 {
+    let (t0, t1) = self.time_range();
+
+    if t <= t0 {
+      return 0.;
+    } else if t >= t1 {
+      return self.length();
+    }
+
+    let i = self.table.iter().position(|&(ti, _)| ti > t).unwrap_or(self.table.len() - 1);
+    let (ta, da) = self.table[i - 1];
+    let (tb, db) = self.table[i];
+
+    da + (db - da) * (t - ta) / (tb - ta)
+  }
+
+  /// Time at which a distance `d` has been traveled along the path. Clamped to the range of the
+  /// table.
+  pub fn time_at_distance(&self, d: f32) -> Time {
+    let (t0, t1) = self.time_range();
+
+    if d <= 0. {
+      return t0;
+    } else if d >= self.length() {
+      return t1;
+    }
+
+    // first entry strictly farther than d; the one before is at most at d
+    let mut lo = 0;
+    let mut hi = self.table.len() - 1;
+
+    while hi - lo > 1 {
+      let mid = (lo + hi) / 2;
+
+      if self.table[mid].1 > d {
+        hi = mid;
+      } else {
+        lo = mid;
+      }
+    }
+
+    let (ta, da) = self.table[lo];
+    let (tb, db) = self.table[hi];
+
+    ta + (tb - ta) * (d - da) / (db - da)
+  }
+
+  /// Time at which the path is traveled at `u` percent (in [0; 1]) of its length. A path of null
+  /// length is always at its start.
+  pub fn constant_speed(&self, u: Time) -> Time {
+    self.time_at_distance(u * self.length())
+  }
+
+  /// Retime `param` so that it’s traveled at constant speed from `start` to `end`. Outside of
+  /// that range, the continuous value is clamped to the ends of the path. If `end` isn’t after
+  /// `start`, the value jumps from the start of the path to its end at `start`.
+  pub fn cont<'a, T>(self, param: AnimParam<T>, start: Time, end: Time, def: T) -> Cont<'a, T> where T: 'a + Interpolate {
+    let mut sampler = Sampler::new();
+
+    Cont::new(move |t| {
+      let u = if end > start {
+        ((t - start) / (end - start)).max(0.).min(1.)
+      } else if t < start {
+        0.
+      } else {
+        1.
+      };
+
+      sample_clamped(&mut sampler, &param, self.constant_speed(u)).unwrap_or(def)
+    })
+  }
+}
+
+// Sample a parameter, accepting the time of its last key as well.
+fn sample_clamped<T>(sampler: &mut Sampler, param: &AnimParam<T>, t: Time) -> Option<T> where T: Interpolate {
+  match sampler.sample(t, param, true) {
+    Some(value) => Some(value),
+    None => {
+      match param.control_points.last() {
+        Some(cp) if t == cp.t => Some(cp.value),
+        _ => None
+      }
+    }
+  }
+}
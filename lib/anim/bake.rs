@@ -0,0 +1,132 @@
+use anim::{AnimParam, Cont, Interpolate, Sampler, Time};
+use anim::arc_length::Metric;
+use anim::keyframe::TrackValue;
+
+/// Animation parameter baked into a table of values sampled at a fixed rate.
+///
+/// Values in between samples are linearly interpolated, so that sampling is O(1) whatever the
+/// number of keys. Outside of the baked range, the first and last samples are held.
+///
+/// Samples are evenly spread over the baked range, the first one at its start and the last one at
+/// its end, so the actual rate might be slightly higher than the requested one.
+#[derive(Clone, Debug)]
+pub struct Baked<T> {
+  start: Time,
+  /// Time between two samples.
+  step: Time,
+  samples: Vec<T>
+}
+
+impl<T> Baked<T> where T: Interpolate {
+  /// Bake `param` from `start` to `end` at `rate` samples per second. `end` can be the time of the
+  /// last key.
+  ///
+  /// Returns `None` if the parameter is not defined over the whole range or if the range is empty.
+  pub fn new(param: &AnimParam<T>, start: Time, end: Time, rate: f32) -> Option<Self> {
+    if !(end > start) || !(rate > 0.) {
+      return None;
+    }
+
+    let n = ((end - start) * rate).ceil() as usize;
+    let step = (end - start) / n as Time;
+    let mut sampler = Sampler::new();
+    let mut samples = Vec::with_capacity(n + 1);
+
+    for i in 0..n + 1 {
+      let t = if i == n { end } else { start + i as Time * step };
+      let value = sampler.sample(t, param, false).or_else(|| {
+        // the last key is excluded from the curve, but it’s where the curve ends
+        param.control_points.last().and_then(|key| if key.t == t { Some(key.value) } else { None })
+      });
+
+      match value {
+        Some(value) => samples.push(value),
+        None => return None
+      }
+    }
+
+    Some(Baked {
+      start: start,
+      step: step,
+      samples: samples
+    })
+  }
+
+  /// Bake the whole range of keys of `param`.
+  pub fn from_param(param: &AnimParam<T>, rate: f32) -> Option<Self> {
+    param.time_range().and_then(|(start, end)| Baked::new(param, start, end, rate))
+  }
+
+  /// Time of the first sample.
+  pub fn start(&self) -> Time {
+    self.start
+  }
+
+  /// Time of the last sample.
+  pub fn end(&self) -> Time {
+    self.start + (self.samples.len() - 1) as Time * self.step
+  }
+
+  /// Actual number of samples per second.
+  pub fn rate(&self) -> f32 {
+    1. / self.step
+  }
+
+  pub fn samples(&self) -> &[T] {
+    &self.samples
+  }
+
+  /// Value at `t`.
+  pub fn at(&self, t: Time) -> T {
+    let last = self.samples.len() - 1;
+    let x = (t - self.start) / self.step;
+
+    if !(x > 0.) {
+      return self.samples[0];
+    }
+
+    let i = x.floor() as usize;
+
+    if i >= last {
+      self.samples[last]
+    } else {
+      T::lerp(self.samples[i], self.samples[i + 1], x - i as f32)
+    }
+  }
+
+  /// Maximum distance between the table and the live curve of `param`, checked `steps` times per
+  /// sample.
+  pub fn max_error(&self, param: &AnimParam<T>, steps: usize) -> f32 where T: Metric {
+    let mut sampler = Sampler::new();
+    let steps = steps.max(1);
+    let n = (self.samples.len() - 1) * steps;
+    let mut error: f32 = 0.;
+
+    for i in 0..n + 1 {
+      let t = self.start + i as Time * self.step / steps as f32;
+
+      if let Some(value) = sampler.sample(t, param, false) {
+        error = error.max(Metric::distance(&value, &self.at(t)));
+      }
+    }
+
+    error
+  }
+
+  /// Raw table, the components of the samples laid out one after the other – e.g. to be uploaded as
+  /// a 1D texture and sampled in shaders.
+  pub fn to_raw(&self) -> Vec<f32> where T: TrackValue {
+    let mut raw = Vec::new();
+
+    for sample in &self.samples {
+      sample.to_components(&mut raw);
+    }
+
+    raw
+  }
+
+  /// Turn the table into a continuous value.
+  pub fn into_cont<'a>(self) -> Cont<'a, T> where T: 'a {
+    Cont::new(move |t| self.at(t))
+  }
+}
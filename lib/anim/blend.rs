@@ -0,0 +1,82 @@
+use anim::{Cont, Interpolate, Time};
+
+/// How a layer combines with what’s below it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode<T> {
+  /// Fade from what’s below to the layer as its weight goes from 0 to 1. Weights are clamped to
+  /// [0; 1].
+  Override,
+  /// Add the difference between the layer and a reference value – its rest pose – scaled by the
+  /// weight of the layer. Weights are not clamped.
+  Additive(T)
+}
+
+/// A track blended on top of other ones.
+pub struct Layer<'a, T> {
+  value: Cont<'a, T>,
+  weight: Cont<'a, f32>,
+  mode: BlendMode<T>
+}
+
+/// Stack of animated values of the same type, blended from bottom to top.
+///
+/// Weights are animated too, so that layers can be faded in and out. Blending relies on
+/// `Interpolate::lerp` and `Interpolate::add_delta`, so orientations blend along the sphere.
+///
+/// ```ignore
+/// let camera_position = Blend::new(base_motion)
+///   .layer(shake, Cont::constant(0.2), BlendMode::Additive(Vector3::new(0., 0., 0.)))
+///   .into_cont();
+/// ```
+pub struct Blend<'a, T> {
+  base: Cont<'a, T>,
+  layers: Vec<Layer<'a, T>>
+}
+
+impl<'a, T> Blend<'a, T> where T: 'a + Interpolate {
+  pub fn new(base: Cont<'a, T>) -> Self {
+    Blend {
+      base: base,
+      layers: Vec::new()
+    }
+  }
+
+  /// Add a layer on top of the others.
+  pub fn layer(mut self, value: Cont<'a, T>, weight: Cont<'a, f32>, mode: BlendMode<T>) -> Self {
+    self.layers.push(Layer {
+      value: value,
+      weight: weight,
+      mode: mode
+    });
+
+    self
+  }
+
+  /// Blended value at `t`.
+  pub fn at(&mut self, t: Time) -> T {
+    let mut value = self.base.at(t);
+
+    for layer in &mut self.layers {
+      let weight = layer.weight.at(t);
+
+      // skip layers that don’t contribute
+      if weight == 0. {
+        continue;
+      }
+
+      let layer_value = layer.value.at(t);
+
+      value = match layer.mode {
+        BlendMode::Override => T::lerp(value, layer_value, weight.max(0.).min(1.)),
+        BlendMode::Additive(reference) => T::add_delta(value, reference, layer_value, weight)
+      };
+    }
+
+    value
+  }
+
+  /// Turn the blend into a continuous value.
+  pub fn into_cont(mut self) -> Cont<'a, T> {
+    Cont::new(move |t| self.at(t))
+  }
+}
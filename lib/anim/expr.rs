@@ -0,0 +1,488 @@
+use std::f32::consts;
+use std::fmt;
+
+use anim::{Cont, Time};
+use noise::noise;
+
+/// Mathematical expression of time, parsed once and evaluated as many times as needed.
+///
+/// Expressions are made of numbers, the usual arithmetic operators (`+`, `-`, `*`, `/`, `%` and
+/// `^` for powers), parentheses, function calls and names. `t` is the time, `pi` and `tau` are
+/// the well-known constants; any other name refers to a value provided when evaluating – another
+/// track or a constant, for instance.
+///
+/// Available functions are `sin`, `cos`, `tan`, `abs`, `sign`, `floor`, `ceil`, `fract`, `sqrt`,
+/// `exp`, `ln`, `noise` (smooth value noise in [0; 1]), `min`, `max`, `pow`, `step`, `clamp`,
+/// `mix` and `smoothstep`, with the same meaning as in GLSL.
+///
+/// ```text
+/// 0.5 + 0.5 * sin(tau * t / 4) * clamp(intensity, 0, 1)
+/// ```
+#[derive(Clone, Debug)]
+pub struct Expr {
+  src: String,
+  root: Node,
+  /// Referenced names, without duplicates; `Node::Ref` indexes this.
+  names: Vec<String>,
+  /// Every reference with its column, in order of appearance.
+  refs: Vec<(usize, String)>
+}
+
+/// Error raised while parsing an expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExprError {
+  /// Column of the error in the expression, starting at 1.
+  pub column: usize,
+  /// What went wrong.
+  pub reason: String
+}
+
+impl fmt::Display for ExprError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "{}: {}", self.column, self.reason)
+  }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+  Number(f32),
+  Time,
+  Ref(usize),
+  Neg(Box<Node>),
+  Binary(Op, Box<Node>, Box<Node>),
+  Call(Function, Vec<Node>)
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Rem,
+  Pow
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Function {
+  Sin,
+  Cos,
+  Tan,
+  Abs,
+  Sign,
+  Floor,
+  Ceil,
+  Fract,
+  Sqrt,
+  Exp,
+  Ln,
+  Noise,
+  Min,
+  Max,
+  Pow,
+  Step,
+  Clamp,
+  Mix,
+  Smoothstep
+}
+
+impl Function {
+  fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "sin" => Some(Function::Sin),
+      "cos" => Some(Function::Cos),
+      "tan" => Some(Function::Tan),
+      "abs" => Some(Function::Abs),
+      "sign" => Some(Function::Sign),
+      "floor" => Some(Function::Floor),
+      "ceil" => Some(Function::Ceil),
+      "fract" => Some(Function::Fract),
+      "sqrt" => Some(Function::Sqrt),
+      "exp" => Some(Function::Exp),
+      "ln" => Some(Function::Ln),
+      "noise" => Some(Function::Noise),
+      "min" => Some(Function::Min),
+      "max" => Some(Function::Max),
+      "pow" => Some(Function::Pow),
+      "step" => Some(Function::Step),
+      "clamp" => Some(Function::Clamp),
+      "mix" => Some(Function::Mix),
+      "smoothstep" => Some(Function::Smoothstep),
+      _ => None
+    }
+  }
+
+  fn arity(&self) -> usize {
+    match *self {
+      Function::Min | Function::Max | Function::Pow | Function::Step => 2,
+      Function::Clamp | Function::Mix | Function::Smoothstep => 3,
+      _ => 1
+    }
+  }
+
+  fn apply(&self, args: &[f32]) -> f32 {
+    match *self {
+      Function::Sin => args[0].sin(),
+      Function::Cos => args[0].cos(),
+      Function::Tan => args[0].tan(),
+      Function::Abs => args[0].abs(),
+      Function::Sign => if args[0] > 0. { 1. } else if args[0] < 0. { -1. } else { 0. },
+      Function::Floor => args[0].floor(),
+      Function::Ceil => args[0].ceil(),
+      Function::Fract => args[0] - args[0].floor(),
+      Function::Sqrt => args[0].sqrt(),
+      Function::Exp => args[0].exp(),
+      Function::Ln => args[0].ln(),
+      Function::Noise => noise(0, args[0]),
+      Function::Min => args[0].min(args[1]),
+      Function::Max => args[0].max(args[1]),
+      Function::Pow => args[0].powf(args[1]),
+      Function::Step => if args[1] < args[0] { 0. } else { 1. },
+      Function::Clamp => args[0].max(args[1]).min(args[2]),
+      Function::Mix => args[0] + (args[1] - args[0]) * args[2],
+      Function::Smoothstep => {
+        let x = ((args[2] - args[0]) / (args[1] - args[0])).max(0.).min(1.);
+        x * x * (3. - 2. * x)
+      }
+    }
+  }
+}
+
+impl Expr {
+  /// Parse an expression.
+  pub fn parse(src: &str) -> Result<Self, ExprError> {
+    let tokens = try!(tokenize(src));
+    let mut parser = Parser {
+      tokens: tokens,
+      i: 0,
+      end: src.chars().count() + 1,
+      names: Vec::new(),
+      refs: Vec::new()
+    };
+
+    let root = try!(parser.expr());
+
+    if let Some(&(column, ref token)) = parser.tokens.get(parser.i) {
+      return Err(ExprError { column: column, reason: format!("unexpected {}", token) });
+    }
+
+    Ok(Expr {
+      src: src.trim().to_owned(),
+      root: root,
+      names: parser.names,
+      refs: parser.refs
+    })
+  }
+
+  /// Source of the expression.
+  pub fn source(&self) -> &str {
+    &self.src
+  }
+
+  /// Every name the expression refers to, with the column it appears at.
+  pub fn references(&self) -> &[(usize, String)] {
+    &self.refs
+  }
+
+  /// Referenced names, without duplicates. `eval_indexed` refers to them by their index in there.
+  pub fn names(&self) -> &[String] {
+    &self.names
+  }
+
+  /// Evaluate the expression at `t`. `lookup` gives the value of a referenced name at a given
+  /// time.
+  pub fn eval<F>(&self, t: Time, lookup: &mut F) -> f32 where F: FnMut(&str, Time) -> f32 {
+    let names = &self.names;
+    eval(&self.root, t, &mut |i, t| lookup(&names[i], t))
+  }
+
+  /// Evaluate the expression at `t`. `lookup` gives the value of a referenced name, given by its
+  /// index in `names`, at a given time – so that names can be resolved once and for all.
+  pub fn eval_indexed<F>(&self, t: Time, lookup: &mut F) -> f32 where F: FnMut(usize, Time) -> f32 {
+    eval(&self.root, t, lookup)
+  }
+
+  /// Turn the expression into a continuous value. `refs` gives the values of the referenced
+  /// names.
+  ///
+  /// Fails with the first name that has no value.
+  pub fn into_cont<'a>(self, mut refs: Vec<(String, Cont<'a, f32>)>) -> Result<Cont<'a, f32>, ExprError> {
+    let mut values = Vec::with_capacity(self.names.len());
+
+    for name in &self.names {
+      match refs.iter().position(|&(ref n, _)| n == name) {
+        Some(i) => values.push(refs.swap_remove(i).1),
+        None => {
+          let column = self.refs.iter().find(|&&(_, ref n)| n == name).map_or(1, |&(column, _)| column);
+          return Err(ExprError { column: column, reason: format!("unknown name {}", name) });
+        }
+      }
+    }
+
+    let root = self.root;
+
+    Ok(Cont::new(move |t| eval(&root, t, &mut |i, t| values[i].at(t))))
+  }
+}
+
+impl fmt::Display for Expr {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "{}", self.src)
+  }
+}
+
+fn eval(node: &Node, t: Time, lookup: &mut FnMut(usize, Time) -> f32) -> f32 {
+  match *node {
+    Node::Number(x) => x,
+    Node::Time => t,
+    Node::Ref(i) => lookup(i, t),
+    Node::Neg(ref a) => -eval(a, t, lookup),
+    Node::Binary(op, ref a, ref b) => {
+      let a = eval(a, t, lookup);
+      let b = eval(b, t, lookup);
+
+      match op {
+        Op::Add => a + b,
+        Op::Sub => a - b,
+        Op::Mul => a * b,
+        Op::Div => a / b,
+        Op::Rem => a % b,
+        Op::Pow => a.powf(b)
+      }
+    },
+    Node::Call(f, ref args) => {
+      let mut values = [0.; 3];
+
+      for (value, arg) in values.iter_mut().zip(args) {
+        *value = eval(arg, t, lookup);
+      }
+
+      f.apply(&values[..args.len()])
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+  Number(f32),
+  Ident(String),
+  Symbol(char)
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      Token::Number(x) => write!(f, "{}", x),
+      Token::Ident(ref name) => write!(f, "{}", name),
+      Token::Symbol(c) => write!(f, "{}", c)
+    }
+  }
+}
+
+// Split an expression into tokens along with their column, starting at 1.
+fn tokenize(src: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+  let chars: Vec<_> = src.chars().collect();
+  let mut tokens = Vec::new();
+  let mut i = 0;
+
+  while i < chars.len() {
+    let c = chars[i];
+    let column = i + 1;
+
+    if c.is_whitespace() {
+      i += 1;
+    } else if c.is_digit(10) || c == '.' {
+      let start = i;
+
+      while i < chars.len() && (chars[i].is_digit(10) || chars[i] == '.') {
+        i += 1;
+      }
+
+      let s: String = chars[start..i].iter().cloned().collect();
+      let x = try!(s.parse().map_err(|_| ExprError { column: column, reason: format!("invalid number {}", s) }));
+      tokens.push((column, Token::Number(x)));
+    } else if c.is_alphabetic() || c == '_' {
+      let start = i;
+
+      while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+        i += 1;
+      }
+
+      tokens.push((column, Token::Ident(chars[start..i].iter().cloned().collect())));
+    } else if "+-*/%^(),".contains(c) {
+      tokens.push((column, Token::Symbol(c)));
+      i += 1;
+    } else {
+      return Err(ExprError { column: column, reason: format!("unexpected character {}", c) });
+    }
+  }
+
+  Ok(tokens)
+}
+
+// Recursive descent parser.
+struct Parser {
+  tokens: Vec<(usize, Token)>,
+  i: usize,
+  // column right after the expression
+  end: usize,
+  names: Vec<String>,
+  refs: Vec<(usize, String)>
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.i).map(|&(_, ref token)| token)
+  }
+
+  fn column(&self) -> usize {
+    self.tokens.get(self.i).map_or(self.end, |&(column, _)| column)
+  }
+
+  fn error<A>(&self, reason: String) -> Result<A, ExprError> {
+    Err(ExprError { column: self.column(), reason: reason })
+  }
+
+  fn symbol(&self, c: char) -> bool {
+    self.peek() == Some(&Token::Symbol(c))
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), ExprError> {
+    if self.symbol(c) {
+      self.i += 1;
+      Ok(())
+    } else {
+      match self.peek().cloned() {
+        Some(token) => self.error(format!("expected {}, found {}", c, token)),
+        None => self.error(format!("expected {}", c))
+      }
+    }
+  }
+
+  // additive := multiplicative (('+' | '-') multiplicative)*
+  fn expr(&mut self) -> Result<Node, ExprError> {
+    let mut a = try!(self.multiplicative());
+
+    loop {
+      let op = if self.symbol('+') { Op::Add } else if self.symbol('-') { Op::Sub } else { return Ok(a) };
+      self.i += 1;
+      let b = try!(self.multiplicative());
+      a = Node::Binary(op, Box::new(a), Box::new(b));
+    }
+  }
+
+  // multiplicative := unary (('*' | '/' | '%') unary)*
+  fn multiplicative(&mut self) -> Result<Node, ExprError> {
+    let mut a = try!(self.unary());
+
+    loop {
+      let op = if self.symbol('*') { Op::Mul } else if self.symbol('/') { Op::Div } else if self.symbol('%') { Op::Rem } else { return Ok(a) };
+      self.i += 1;
+      let b = try!(self.unary());
+      a = Node::Binary(op, Box::new(a), Box::new(b));
+    }
+  }
+
+  // unary := '-' unary | power
+  fn unary(&mut self) -> Result<Node, ExprError> {
+    if self.symbol('-') {
+      self.i += 1;
+      let a = try!(self.unary());
+      Ok(Node::Neg(Box::new(a)))
+    } else {
+      self.power()
+    }
+  }
+
+  // power := primary ('^' unary)?
+  fn power(&mut self) -> Result<Node, ExprError> {
+    let a = try!(self.primary());
+
+    if self.symbol('^') {
+      self.i += 1;
+      let b = try!(self.unary());
+      Ok(Node::Binary(Op::Pow, Box::new(a), Box::new(b)))
+    } else {
+      Ok(a)
+    }
+  }
+
+  // primary := number | name | name '(' args ')' | '(' expr ')'
+  fn primary(&mut self) -> Result<Node, ExprError> {
+    let column = self.column();
+
+    match self.peek().cloned() {
+      Some(Token::Number(x)) => {
+        self.i += 1;
+        Ok(Node::Number(x))
+      },
+      Some(Token::Ident(name)) => {
+        self.i += 1;
+
+        if self.symbol('(') {
+          self.call(column, &name)
+        } else {
+          Ok(self.name(column, name))
+        }
+      },
+      Some(Token::Symbol('(')) => {
+        self.i += 1;
+        let a = try!(self.expr());
+        try!(self.expect(')'));
+        Ok(a)
+      },
+      Some(token) => self.error(format!("unexpected {}", token)),
+      None => self.error("unexpected end of expression".to_owned())
+    }
+  }
+
+  fn call(&mut self, column: usize, name: &str) -> Result<Node, ExprError> {
+    let f = match Function::from_name(name) {
+      Some(f) => f,
+      None => return Err(ExprError { column: column, reason: format!("unknown function {}", name) })
+    };
+
+    try!(self.expect('('));
+    let mut args = Vec::new();
+
+    if !self.symbol(')') {
+      loop {
+        args.push(try!(self.expr()));
+
+        if self.symbol(',') {
+          self.i += 1;
+        } else {
+          break;
+        }
+      }
+    }
+
+    try!(self.expect(')'));
+
+    if args.len() != f.arity() {
+      return Err(ExprError { column: column, reason: format!("{} expects {} arguments, found {}", name, f.arity(), args.len()) });
+    }
+
+    Ok(Node::Call(f, args))
+  }
+
+  fn name(&mut self, column: usize, name: String) -> Node {
+    match &name[..] {
+      "t" => Node::Time,
+      "pi" => Node::Number(consts::PI),
+      "tau" => Node::Number(2. * consts::PI),
+      _ => {
+        let i = match self.names.iter().position(|n| *n == name) {
+          Some(i) => i,
+          None => {
+            self.names.push(name.clone());
+            self.names.len() - 1
+          }
+        };
+
+        self.refs.push((column, name));
+        Node::Ref(i)
+      }
+    }
+  }
+}
@@ -0,0 +1,53 @@
+use anim::{AnimParam, Interpolate, Interpolation, Key, Sampler, Time};
+use anim::arc_length::Metric;
+
+/// Reduce a dense series of `(time, value)` samples – typically captured one per frame – into a
+/// sparse animation parameter.
+///
+/// Keys are picked among the samples and use the `interpolation` mode – `Linear` and
+/// `CatmullRom` are the ones that make sense here. We start with the first and last samples and
+/// keep on adding the sample the curve is the farthest from, until every sample is within
+/// `tolerance` of the resampled curve.
+///
+/// `samples` must be sorted by time.
+pub fn reduce_keys<T>(samples: &[(Time, T)], tolerance: f32, interpolation: Interpolation) -> AnimParam<T> where T: Interpolate + Metric {
+  if samples.len() <= 2 {
+    return AnimParam::new(samples.iter().map(|&(t, v)| Key::new(t, v, interpolation)).collect());
+  }
+
+  let mut sampler = Sampler::new();
+  // indices of the samples used as keys, kept sorted
+  let mut picked = vec![0, samples.len() - 1];
+  let mut param = AnimParam::new(picked.iter().map(|&i| Key::new(samples[i].0, samples[i].1, interpolation)).collect());
+
+  loop {
+    // find the worst sample; keys are exact, so skip them
+    let mut worst = None;
+    let mut worst_err = tolerance;
+
+    for (i, &(t, v)) in samples.iter().enumerate() {
+      if picked.binary_search(&i).is_ok() {
+        continue;
+      }
+
+      let err = match sampler.sample(t, &param, false) {
+        Some(r) => Metric::distance(&r, &v),
+        None => continue
+      };
+
+      if err > worst_err {
+        worst = Some(i);
+        worst_err = err;
+      }
+    }
+
+    match worst {
+      Some(i) => {
+        let pos = picked.binary_search(&i).unwrap_err();
+        picked.insert(pos, i);
+        param.insert(Key::new(samples[i].0, samples[i].1, interpolation));
+      },
+      None => return param
+    }
+  }
+}
@@ -0,0 +1,97 @@
+use anim::Time;
+
+/// Default simulation step, in seconds.
+pub const DEFAULT_STEP: Time = 1. / 240.;
+
+/// Number of simulation steps between two checkpoints.
+const CHECKPOINT_STEPS: u64 = 256;
+
+/// Something simulated step by step, such as a spring or an integrated frequency.
+pub trait Simulation {
+  type State: Copy;
+
+  /// State at the start time.
+  fn initial_state(&mut self) -> Self::State;
+  /// Advance `state` by `dt`, from time `t`.
+  fn advance(&mut self, state: Self::State, t: Time, dt: Time) -> Self::State;
+}
+
+/// Runs a `Simulation` with a fixed step, on a time grid starting at a fixed time, so that the
+/// state at a given time only depends on that time: going back in time resimulates from the
+/// closest checkpoint and gives exactly the same states again.
+#[derive(Clone, Debug)]
+pub struct FixedStep<S> {
+  start: Time,
+  step: Time,
+  /// Last state simulated on the grid, along with its index on the grid.
+  last: Option<(u64, S)>,
+  /// States saved along the way, sorted by index; the first one is the initial state.
+  checkpoints: Vec<(u64, S)>
+}
+
+impl<S> FixedStep<S> where S: Copy {
+  pub fn new(start: Time, step: Time) -> Self {
+    FixedStep {
+      start: start,
+      step: step,
+      last: None,
+      checkpoints: Vec::new()
+    }
+  }
+
+  pub fn start(&self) -> Time {
+    self.start
+  }
+
+  pub fn step(&self) -> Time {
+    self.step
+  }
+
+  /// State of `sim` at `t`. Times before the start time give the initial state.
+  ///
+  /// The same `sim` must be used every time; the states of another one are not forgotten.
+  pub fn at<M>(&mut self, sim: &mut M, t: Time) -> S where M: Simulation<State = S> {
+    let n = ((t - self.start) / self.step).floor().max(0.) as u64;
+
+    // resume from the last state if we’re going forward, or from a checkpoint otherwise
+    let (mut i, mut state) = match self.last {
+      Some((i, state)) if i <= n => (i, state),
+      _ => self.checkpoint_before(sim, n)
+    };
+
+    while i < n {
+      state = sim.advance(state, self.grid_time(i), self.step);
+      i += 1;
+
+      if i % CHECKPOINT_STEPS == 0 && self.checkpoints.last().map_or(true, |&(c, _)| c < i) {
+        self.checkpoints.push((i, state));
+      }
+    }
+
+    self.last = Some((i, state));
+
+    // finish with a partial step, which is not kept
+    let t0 = self.grid_time(n);
+    let rest = t - t0;
+
+    if rest > 0. {
+      sim.advance(state, t0, rest)
+    } else {
+      state
+    }
+  }
+
+  // Time of a step on the grid.
+  fn grid_time(&self, i: u64) -> Time {
+    self.start + i as Time * self.step
+  }
+
+  // Latest checkpoint at or before step `n`.
+  fn checkpoint_before<M>(&mut self, sim: &mut M, n: u64) -> (u64, S) where M: Simulation<State = S> {
+    if self.checkpoints.is_empty() {
+      self.checkpoints.push((0, sim.initial_state()));
+    }
+
+    *self.checkpoints.iter().rev().find(|&&(i, _)| i <= n).unwrap_or(&self.checkpoints[0])
+  }
+}
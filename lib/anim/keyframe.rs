@@ -0,0 +1,1280 @@
+use anim::{AnimParam, Cont, Extrapolation, Handle, Interpolate, Interpolation, Key, Sampler, Time};
+use anim::expr::Expr;
+use anim::tempo::{MusicalTime, TempoMap, TimeSignature};
+use color::Color;
+use nalgebra::{Quaternion, UnitQuaternion, Vector2, Vector3, Vector4};
+use resource::*;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// A set of named, typed animation tracks, usually read from a keyframe file.
+///
+/// Keyframe files are line-based text files. Everything following a `#` is a comment. A track
+/// starts with a `track <name> <type>` line, `<type>` being one of `f32`, `vec2`, `vec3`, `vec4`,
+/// `color` or `orientation`. Each following line is a key of that track:
+///
+/// ```text
+/// <time> <interpolation> <value>
+/// ```
+///
+/// A track line might end with `pre <extrapolation>` and `post <extrapolation>` to define the track
+/// before its first key and after its last one, `<extrapolation>` being one of `constant`,
+/// `linear`, `cycle`, `cycle-offset` or `ping-pong`.
+///
+/// `<interpolation>` is one of `hold`, `linear`, `cosine`, `catmull-rom`, `bezier` or an easing
+/// mode such as `ease-in-quad`, `ease-out-back` or `ease-in-out-bounce`. `<value>` is made of as
+/// many numbers as the track type has components – orientations are written as `w i j k`
+/// quaternions. Keys might carry their tangent handles, written as
+/// `in <time> <value> out <time> <value>`; they shape the `bezier` segments around the key.
+///
+/// ```text
+/// track camera_position vec3 post constant
+///   0    hold   0 0 0
+///   4.69 linear -5.978943 -0.08311983 -2.977364
+///   9    bezier -7 -0.15 -2.96 in 8.5 -7 -0.15 -2.96 out 9.5 -7 -0.15 -2.96
+/// ```
+///
+/// Before the first track, a file might declare a tempo map with a `tempo <bpm> <signature>`
+/// line, optionally followed by `offset <seconds>`, and tempo changes with
+/// `tempo <bpm> at <position>` lines. Key and handle times can then be written in musical time,
+/// as `bar:beat` or `bar:beat:tick` positions (see `MusicalTime`).
+///
+/// ```text
+/// tempo 128 4/4 offset 0.35
+/// tempo 140 at 17:1
+///
+/// track flash f32
+///   1:1   linear 0
+///   1:2:240 linear 1
+/// ```
+///
+/// When keyframes with a tempo map are written, times that fall exactly on a tick are written as
+/// musical positions, the other ones in seconds, so that reading them back gives the same times.
+///
+/// Constants are defined with `const <name> <value>` lines, and procedural `f32` values with
+/// `expr <name> <expression>` lines (see `Expr`). Expressions can refer to constants, to other
+/// expressions and to `f32` tracks, defined anywhere in the file.
+///
+/// ```text
+/// const speed 4
+/// expr pulse fract(t * speed) * flash
+/// ```
+///
+/// That’s also how keys and procedural motion are combined in a single parameter: key the overall
+/// shape in a track, and refer to it from an expression that adds the details.
+///
+/// ```text
+/// expr glow flash * (0.8 + 0.2 * noise(t * 10))
+///
+/// track flash f32
+///   0 cosine 0
+///   2 hold   1
+/// ```
+#[derive(Clone, Debug)]
+pub struct Keyframes {
+  tracks: Vec<Track>,
+  tempo: Option<TempoMap>,
+  consts: Vec<(String, f32)>,
+  exprs: Vec<(String, Expr)>,
+  /// What the names of each expression refer to, resolved when parsing.
+  bindings: Vec<Vec<Binding>>
+}
+
+// What a name resolves to.
+#[derive(Clone, Copy, Debug)]
+enum Binding {
+  Const(f32),
+  Expr(usize),
+  Track(usize)
+}
+
+/// A named track of a `Keyframes`.
+#[derive(Clone, Debug)]
+pub struct Track {
+  pub name: String,
+  pub keys: TrackKeys
+}
+
+/// Keys of a track, along with their type.
+#[derive(Clone, Debug)]
+pub enum TrackKeys {
+  F32(AnimParam<f32>),
+  Vec2(AnimParam<Vector2<f32>>),
+  Vec3(AnimParam<Vector3<f32>>),
+  Vec4(AnimParam<Vector4<f32>>),
+  Color(AnimParam<Color>),
+  Orientation(AnimParam<UnitQuaternion<f32>>)
+}
+
+impl TrackKeys {
+  /// Name of the type of the track, as found in keyframe files.
+  pub fn type_name(&self) -> &'static str {
+    match *self {
+      TrackKeys::F32(_) => "f32",
+      TrackKeys::Vec2(_) => "vec2",
+      TrackKeys::Vec3(_) => "vec3",
+      TrackKeys::Vec4(_) => "vec4",
+      TrackKeys::Color(_) => "color",
+      TrackKeys::Orientation(_) => "orientation"
+    }
+  }
+}
+
+impl TrackKeys {
+  /// Extrapolation of the keys, before the first one and after the last one.
+  pub fn extrapolation(&self) -> (Option<Extrapolation>, Option<Extrapolation>) {
+    match *self {
+      TrackKeys::F32(ref param) => (param.pre_extrapolation(), param.post_extrapolation()),
+      TrackKeys::Vec2(ref param) => (param.pre_extrapolation(), param.post_extrapolation()),
+      TrackKeys::Vec3(ref param) => (param.pre_extrapolation(), param.post_extrapolation()),
+      TrackKeys::Vec4(ref param) => (param.pre_extrapolation(), param.post_extrapolation()),
+      TrackKeys::Color(ref param) => (param.pre_extrapolation(), param.post_extrapolation()),
+      TrackKeys::Orientation(ref param) => (param.pre_extrapolation(), param.post_extrapolation())
+    }
+  }
+
+  /// Set the extrapolation of the keys. See `AnimParam::with_extrapolation`.
+  pub fn with_extrapolation(self, pre: Option<Extrapolation>, post: Option<Extrapolation>) -> Self {
+    match self {
+      TrackKeys::F32(param) => TrackKeys::F32(param.with_extrapolation(pre, post)),
+      TrackKeys::Vec2(param) => TrackKeys::Vec2(param.with_extrapolation(pre, post)),
+      TrackKeys::Vec3(param) => TrackKeys::Vec3(param.with_extrapolation(pre, post)),
+      TrackKeys::Vec4(param) => TrackKeys::Vec4(param.with_extrapolation(pre, post)),
+      TrackKeys::Color(param) => TrackKeys::Color(param.with_extrapolation(pre, post)),
+      TrackKeys::Orientation(param) => TrackKeys::Orientation(param.with_extrapolation(pre, post))
+    }
+  }
+
+  /// Sample the keys at `t`.
+  pub fn sample(&self, sampler: &mut Sampler, t: Time) -> Option<TrackSample> {
+    match *self {
+      TrackKeys::F32(ref param) => sampler.sample(t, param, true).map(TrackSample::F32),
+      TrackKeys::Vec2(ref param) => sampler.sample(t, param, true).map(TrackSample::Vec2),
+      TrackKeys::Vec3(ref param) => sampler.sample(t, param, true).map(TrackSample::Vec3),
+      TrackKeys::Vec4(ref param) => sampler.sample(t, param, true).map(TrackSample::Vec4),
+      TrackKeys::Color(ref param) => sampler.sample(t, param, true).map(TrackSample::Color),
+      TrackKeys::Orientation(ref param) => sampler.sample(t, param, true).map(TrackSample::Orientation)
+    }
+  }
+
+  /// Time of the first and last keys, if any.
+  pub fn time_range(&self) -> Option<(Time, Time)> {
+    match *self {
+      TrackKeys::F32(ref param) => param.time_range(),
+      TrackKeys::Vec2(ref param) => param.time_range(),
+      TrackKeys::Vec3(ref param) => param.time_range(),
+      TrackKeys::Vec4(ref param) => param.time_range(),
+      TrackKeys::Color(ref param) => param.time_range(),
+      TrackKeys::Orientation(ref param) => param.time_range()
+    }
+  }
+}
+
+/// A sampled value of a track, along with its type.
+#[derive(Copy, Clone, Debug)]
+pub enum TrackSample {
+  F32(f32),
+  Vec2(Vector2<f32>),
+  Vec3(Vector3<f32>),
+  Vec4(Vector4<f32>),
+  Color(Color),
+  Orientation(UnitQuaternion<f32>)
+}
+
+/// Types that can be stored in a keyframe file.
+pub trait TrackValue: Interpolate {
+  /// Get the keys of a track if they have the right type.
+  fn keys(keys: &TrackKeys) -> Option<&AnimParam<Self>>;
+  /// Wrap keys into a typed track.
+  fn into_keys(param: AnimParam<Self>) -> TrackKeys;
+  /// Get a sampled value if it has the right type.
+  fn from_sample(sample: &TrackSample) -> Option<Self>;
+  /// Build a value out of its components. `components` has the right length.
+  fn from_components(components: &[f32]) -> Self;
+  /// Append the components of a value to `components`.
+  fn to_components(&self, components: &mut Vec<f32>);
+}
+
+impl TrackValue for f32 {
+  fn keys(keys: &TrackKeys) -> Option<&AnimParam<Self>> {
+    match *keys {
+      TrackKeys::F32(ref param) => Some(param),
+      _ => None
+    }
+  }
+
+  fn into_keys(param: AnimParam<Self>) -> TrackKeys {
+    TrackKeys::F32(param)
+  }
+
+  fn from_sample(sample: &TrackSample) -> Option<Self> {
+    match *sample {
+      TrackSample::F32(x) => Some(x),
+      _ => None
+    }
+  }
+
+  fn from_components(components: &[f32]) -> Self {
+    components[0]
+  }
+
+  fn to_components(&self, components: &mut Vec<f32>) {
+    components.push(*self);
+  }
+}
+
+impl TrackValue for Vector2<f32> {
+  fn keys(keys: &TrackKeys) -> Option<&AnimParam<Self>> {
+    match *keys {
+      TrackKeys::Vec2(ref param) => Some(param),
+      _ => None
+    }
+  }
+
+  fn into_keys(param: AnimParam<Self>) -> TrackKeys {
+    TrackKeys::Vec2(param)
+  }
+
+  fn from_sample(sample: &TrackSample) -> Option<Self> {
+    match *sample {
+      TrackSample::Vec2(x) => Some(x),
+      _ => None
+    }
+  }
+
+  fn from_components(components: &[f32]) -> Self {
+    Vector2::new(components[0], components[1])
+  }
+
+  fn to_components(&self, components: &mut Vec<f32>) {
+    components.extend(&[self.x, self.y]);
+  }
+}
+
+// Colors are also three-dimensional vectors, so both track types are accepted.
+impl TrackValue for Vector3<f32> {
+  fn keys(keys: &TrackKeys) -> Option<&AnimParam<Self>> {
+    match *keys {
+      TrackKeys::Vec3(ref param) | TrackKeys::Color(ref param) => Some(param),
+      _ => None
+    }
+  }
+
+  fn into_keys(param: AnimParam<Self>) -> TrackKeys {
+    TrackKeys::Vec3(param)
+  }
+
+  fn from_sample(sample: &TrackSample) -> Option<Self> {
+    match *sample {
+      TrackSample::Vec3(x) | TrackSample::Color(x) => Some(x),
+      _ => None
+    }
+  }
+
+  fn from_components(components: &[f32]) -> Self {
+    Vector3::new(components[0], components[1], components[2])
+  }
+
+  fn to_components(&self, components: &mut Vec<f32>) {
+    components.extend(&[self.x, self.y, self.z]);
+  }
+}
+
+impl TrackValue for Vector4<f32> {
+  fn keys(keys: &TrackKeys) -> Option<&AnimParam<Self>> {
+    match *keys {
+      TrackKeys::Vec4(ref param) => Some(param),
+      _ => None
+    }
+  }
+
+  fn into_keys(param: AnimParam<Self>) -> TrackKeys {
+    TrackKeys::Vec4(param)
+  }
+
+  fn from_sample(sample: &TrackSample) -> Option<Self> {
+    match *sample {
+      TrackSample::Vec4(x) => Some(x),
+      _ => None
+    }
+  }
+
+  fn from_components(components: &[f32]) -> Self {
+    Vector4::new(components[0], components[1], components[2], components[3])
+  }
+
+  fn to_components(&self, components: &mut Vec<f32>) {
+    components.extend(&[self.x, self.y, self.z, self.w]);
+  }
+}
+
+impl TrackValue for UnitQuaternion<f32> {
+  fn keys(keys: &TrackKeys) -> Option<&AnimParam<Self>> {
+    match *keys {
+      TrackKeys::Orientation(ref param) => Some(param),
+      _ => None
+    }
+  }
+
+  fn into_keys(param: AnimParam<Self>) -> TrackKeys {
+    TrackKeys::Orientation(param)
+  }
+
+  fn from_sample(sample: &TrackSample) -> Option<Self> {
+    match *sample {
+      TrackSample::Orientation(x) => Some(x),
+      _ => None
+    }
+  }
+
+  fn from_components(components: &[f32]) -> Self {
+    UnitQuaternion::new_with_quaternion(Quaternion::new(components[0], components[1], components[2], components[3]))
+  }
+
+  fn to_components(&self, components: &mut Vec<f32>) {
+    let q = self.quaternion();
+    components.extend(&[q.w, q.i, q.j, q.k]);
+  }
+}
+
+/// Error that might occur while loading a keyframe file.
+#[derive(Debug)]
+pub enum KeyframeError {
+  /// The file couldn’t be read.
+  IoError(String, io::Error),
+  /// The file is ill-formed.
+  ParseError(ParseError)
+}
+
+impl fmt::Display for KeyframeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      KeyframeError::IoError(ref file, ref e) => write!(f, "{}: {}", file, e),
+      KeyframeError::ParseError(ref e) => e.fmt(f)
+    }
+  }
+}
+
+/// Parse error, located in its file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+  /// File in which the error occurred.
+  pub file: String,
+  /// Line of the error, starting at 1.
+  pub line: usize,
+  /// Column of the error, starting at 1.
+  pub column: usize,
+  /// What went wrong.
+  pub reason: String
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.reason)
+  }
+}
+
+impl Keyframes {
+  pub fn new() -> Self {
+    Keyframes {
+      tracks: Vec::new(),
+      tempo: None,
+      consts: Vec::new(),
+      exprs: Vec::new(),
+      bindings: Vec::new()
+    }
+  }
+
+  /// Load a keyframe file.
+  pub fn load<P>(path: P) -> Result<Self, KeyframeError> where P: AsRef<Path> {
+    let path = path.as_ref();
+    let file = path.display().to_string();
+    let mut src = String::new();
+
+    try!(fs::File::open(path).and_then(|mut fh| fh.read_to_string(&mut src)).map_err(|e| KeyframeError::IoError(file.clone(), e)));
+
+    Keyframes::parse(&file, &src).map_err(KeyframeError::ParseError)
+  }
+
+  /// Parse the content of a keyframe file. `file` is only used to report errors.
+  pub fn parse(file: &str, src: &str) -> Result<Self, ParseError> {
+    let mut keyframes = Keyframes::new();
+    // track being parsed, with its type and its raw keys
+    let mut current: Option<(String, ValueType, Vec<Key<Vec<f32>>>, (Option<Extrapolation>, Option<Extrapolation>))> = None;
+    // line and column of each expression, to report reference errors
+    let mut expr_locations = Vec::new();
+
+    for (line_i, line) in src.lines().enumerate() {
+      let tokens = tokenize(line);
+      let error = |column: usize, reason: String| {
+        ParseError {
+          file: file.to_owned(),
+          line: line_i + 1,
+          column: column,
+          reason: reason
+        }
+      };
+
+      if tokens.is_empty() {
+        continue;
+      }
+
+      if tokens[0].1 == "tempo" {
+        if current.is_some() || !keyframes.tracks.is_empty() {
+          return Err(error(tokens[0].0, "tempo must be defined before the tracks".to_owned()));
+        }
+
+        let tempo = keyframes.tempo.take();
+        keyframes.tempo = Some(try!(parse_tempo(&tokens, tempo).map_err(|(column, reason)| error(column, reason))));
+      } else if tokens[0].1 == "track" {
+        if tokens.len() < 3 {
+          return Err(error(tokens[0].0, "expected track <name> <type>".to_owned()));
+        }
+
+        let (name_col, name) = tokens[1];
+        let (ty_col, ty) = tokens[2];
+        let ty = try!(ValueType::from_name(ty).ok_or_else(|| error(ty_col, format!("unknown track type {}", ty))));
+        let extrapolation = try!(parse_extrapolation(&tokens[3..]).map_err(|(column, reason)| error(column, reason)));
+
+        if let Some((prev_name, prev_ty, prev_keys, (pre, post))) = current.take() {
+          keyframes.insert(&prev_name, prev_ty.to_track_keys(prev_keys).with_extrapolation(pre, post));
+        }
+
+        if keyframes.is_defined(name) {
+          return Err(error(name_col, format!("{} is already defined", name)));
+        }
+
+        current = Some((name.to_owned(), ty, Vec::new(), extrapolation));
+      } else if tokens[0].1 == "const" || tokens[0].1 == "expr" {
+        if tokens.len() < 3 {
+          return Err(error(tokens[0].0, format!("expected {} <name> <value>", tokens[0].1)));
+        }
+
+        // constants and expressions end the current track
+        if let Some((prev_name, prev_ty, prev_keys, (pre, post))) = current.take() {
+          keyframes.insert(&prev_name, prev_ty.to_track_keys(prev_keys).with_extrapolation(pre, post));
+        }
+
+        let (name_col, name) = tokens[1];
+
+        if keyframes.is_defined(name) {
+          return Err(error(name_col, format!("{} is already defined", name)));
+        }
+
+        if tokens[0].1 == "const" {
+          let value = try!(parse_number(tokens[2], "a number").map_err(|(column, reason)| error(column, reason)));
+
+          if let Some(&(column, token)) = tokens.get(3) {
+            return Err(error(column, format!("unexpected {}", token)));
+          }
+
+          keyframes.consts.push((name.to_owned(), value));
+        } else {
+          // the expression is the rest of the line
+          let start = tokens[2].0;
+          let src: String = line.split('#').next().unwrap_or("").chars().skip(start - 1).collect();
+          let expr = try!(Expr::parse(&src).map_err(|e| error(start + e.column - 1, e.reason)));
+
+          keyframes.exprs.push((name.to_owned(), expr));
+          expr_locations.push((line_i + 1, start));
+        }
+      } else {
+        let (ty, keys) = match current {
+          Some((_, ty, ref mut keys, _)) => (ty, keys),
+          None => return Err(error(tokens[0].0, "key defined outside of a track".to_owned()))
+        };
+
+        let key = try!(parse_key(&tokens, ty.arity(), keyframes.tempo.as_ref()).map_err(|(column, reason)| error(column, reason)));
+        keys.push(key);
+      }
+    }
+
+    if let Some((name, ty, keys, (pre, post))) = current.take() {
+      keyframes.insert(&name, ty.to_track_keys(keys).with_extrapolation(pre, post));
+    }
+
+    try!(keyframes.check_exprs().map_err(|(i, column, reason)| {
+      let (line, start) = expr_locations[i];
+
+      ParseError {
+        file: file.to_owned(),
+        line: line,
+        column: start + column - 1,
+        reason: reason
+      }
+    }));
+
+    keyframes.bindings = keyframes.exprs.iter().map(|&(_, ref expr)| {
+      // all the names are defined, since the expressions were checked
+      expr.names().iter().map(|name| keyframes.binding(name).unwrap()).collect()
+    }).collect();
+
+    Ok(keyframes)
+  }
+
+  // Check that expressions only refer to defined values and don’t depend on themselves. Errors
+  // carry the index of the expression and the column in it.
+  fn check_exprs(&self) -> Result<(), (usize, usize, String)> {
+    for (i, &(_, ref expr)) in self.exprs.iter().enumerate() {
+      for &(column, ref name) in expr.references() {
+        let defined = self.consts.iter().any(|&(ref n, _)| n == name) ||
+                      self.exprs.iter().any(|&(ref n, _)| n == name) ||
+                      self.get::<f32>(name).is_some();
+
+        if !defined {
+          let reason = match self.keys(name) {
+            Some(keys) => format!("{} is a {} track, expected f32", name, keys.type_name()),
+            None => format!("unknown name {}", name)
+          };
+
+          return Err((i, column, reason));
+        }
+      }
+    }
+
+    // depth-first search of dependency cycles; 0 is unvisited, 1 in progress, 2 done
+    let mut marks = vec![0; self.exprs.len()];
+
+    for i in 0..self.exprs.len() {
+      try!(self.visit_expr(i, &mut marks));
+    }
+
+    Ok(())
+  }
+
+  fn visit_expr(&self, i: usize, marks: &mut Vec<u8>) -> Result<(), (usize, usize, String)> {
+    if marks[i] == 2 {
+      return Ok(());
+    }
+
+    marks[i] = 1;
+
+    for &(column, ref name) in self.exprs[i].1.references() {
+      if let Some(j) = self.exprs.iter().position(|&(ref n, _)| n == name) {
+        if marks[j] == 1 {
+          return Err((i, column, format!("{} depends on itself", name)));
+        }
+
+        try!(self.visit_expr(j, marks));
+      }
+    }
+
+    marks[i] = 2;
+    Ok(())
+  }
+
+  // Is a name already taken by a track, a constant or an expression?
+  fn is_defined(&self, name: &str) -> bool {
+    self.keys(name).is_some() || self.consts.iter().any(|&(ref n, _)| n == name) || self.exprs.iter().any(|&(ref n, _)| n == name)
+  }
+
+  /// Write the keyframes into a file, in a format that can be parsed back.
+  pub fn save<P>(&self, path: P) -> io::Result<()> where P: AsRef<Path> {
+    let mut fh = try!(fs::File::create(path));
+    self.write(&mut fh)
+  }
+
+  /// Write the keyframes, in a format that can be parsed back.
+  pub fn write<W>(&self, w: &mut W) -> io::Result<()> where W: Write {
+    write!(w, "{}", self)
+  }
+
+  /// Tempo map the key times were authored with, if any.
+  pub fn tempo(&self) -> Option<&TempoMap> {
+    self.tempo.as_ref()
+  }
+
+  /// Set the tempo map. Key times are left untouched.
+  pub fn set_tempo(&mut self, tempo: Option<TempoMap>) {
+    self.tempo = tempo;
+  }
+
+  /// Constants, in their definition order.
+  pub fn consts(&self) -> &[(String, f32)] {
+    &self.consts
+  }
+
+  /// Expressions, in their definition order.
+  pub fn exprs(&self) -> &[(String, Expr)] {
+    &self.exprs
+  }
+
+  /// Value of a constant, an expression or a `f32` track at `t`. Returns `None` if there’s no such
+  /// value or if it isn’t defined at that time.
+  pub fn value(&self, name: &str, t: Time) -> Option<f32> {
+    self.binding(name).and_then(|binding| self.resolve(binding, t, &mut Sampler::new()))
+  }
+
+  // What a name refers to.
+  fn binding(&self, name: &str) -> Option<Binding> {
+    if let Some(&(_, value)) = self.consts.iter().find(|&&(ref n, _)| n == name) {
+      return Some(Binding::Const(value));
+    }
+
+    if let Some(i) = self.exprs.iter().position(|&(ref n, _)| n == name) {
+      return Some(Binding::Expr(i));
+    }
+
+    self.tracks.iter().position(|track| track.name == name).map(Binding::Track)
+  }
+
+  // Value of a binding at `t`, if defined. Names referred to by expressions were resolved when
+  // parsing, so nothing is looked up by name here.
+  fn resolve(&self, binding: Binding, t: Time, sampler: &mut Sampler) -> Option<f32> {
+    match binding {
+      Binding::Const(value) => Some(value),
+      Binding::Expr(i) => {
+        let bindings = &self.bindings[i];
+        let mut defined = true;
+        let value = self.exprs[i].1.eval_indexed(t, &mut |j, t| {
+          self.resolve(bindings[j], t, sampler).unwrap_or_else(|| {
+            defined = false;
+            0.
+          })
+        });
+
+        if defined { Some(value) } else { None }
+      },
+      Binding::Track(i) => {
+        match self.tracks[i].keys {
+          TrackKeys::F32(ref param) => sampler.sample(t, param, true),
+          _ => None
+        }
+      }
+    }
+  }
+
+  /// All the tracks, in their definition order.
+  pub fn tracks(&self) -> &[Track] {
+    &self.tracks
+  }
+
+  /// Get the keys of a track, whatever its type.
+  pub fn keys(&self, name: &str) -> Option<&TrackKeys> {
+    self.tracks.iter().find(|track| track.name == name).map(|track| &track.keys)
+  }
+
+  /// Get a track by its name. Returns `None` if the track doesn’t exist or if it doesn’t have the
+  /// expected type.
+  pub fn get<T>(&self, name: &str) -> Option<&AnimParam<T>> where T: TrackValue {
+    self.keys(name).and_then(T::keys)
+  }
+
+  /// Add a track. If a track with the same name already exists, it’s replaced.
+  pub fn insert(&mut self, name: &str, keys: TrackKeys) {
+    match self.tracks.iter().position(|track| track.name == name) {
+      Some(i) => self.tracks[i].keys = keys,
+      None => self.tracks.push(Track { name: name.to_owned(), keys: keys })
+    }
+  }
+}
+
+impl fmt::Display for Keyframes {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    if let Some(ref tempo) = self.tempo {
+      try!(write!(f, "tempo {} {}", tempo.bpm(), tempo.signature()));
+
+      if tempo.offset() != 0. {
+        try!(write!(f, " offset {}", tempo.offset()));
+      }
+
+      try!(writeln!(f, ""));
+
+      for (at, bpm) in tempo.tempo_changes() {
+        try!(writeln!(f, "tempo {} at {}", bpm, at));
+      }
+
+      if !self.tracks.is_empty() || !self.consts.is_empty() || !self.exprs.is_empty() {
+        try!(writeln!(f, ""));
+      }
+    }
+
+    for &(ref name, value) in &self.consts {
+      try!(writeln!(f, "const {} {}", name, value));
+    }
+
+    for &(ref name, ref expr) in &self.exprs {
+      try!(writeln!(f, "expr {} {}", name, expr));
+    }
+
+    if (!self.consts.is_empty() || !self.exprs.is_empty()) && !self.tracks.is_empty() {
+      try!(writeln!(f, ""));
+    }
+
+    for (i, track) in self.tracks.iter().enumerate() {
+      if i > 0 {
+        try!(writeln!(f, ""));
+      }
+
+      try!(write!(f, "track {} {}", track.name, track.keys.type_name()));
+
+      let (pre, post) = track.keys.extrapolation();
+
+      if let Some(pre) = pre {
+        try!(write!(f, " pre {}", extrapolation_name(pre)));
+      }
+
+      if let Some(post) = post {
+        try!(write!(f, " post {}", extrapolation_name(post)));
+      }
+
+      try!(writeln!(f, ""));
+
+      let tempo = self.tempo.as_ref();
+
+      try!(match track.keys {
+        TrackKeys::F32(ref param) => write_keys(f, param, tempo),
+        TrackKeys::Vec2(ref param) => write_keys(f, param, tempo),
+        TrackKeys::Vec3(ref param) => write_keys(f, param, tempo),
+        TrackKeys::Vec4(ref param) => write_keys(f, param, tempo),
+        TrackKeys::Color(ref param) => write_keys(f, param, tempo),
+        TrackKeys::Orientation(ref param) => write_keys(f, param, tempo)
+      });
+    }
+
+    Ok(())
+  }
+}
+
+/// A keyframe file handled as a resource.
+///
+/// Reloading a `KeyframeFile` swaps its tracks in place: every `Managed<KeyframeFile>` – and every
+/// `Cont` built with `KeyframeFile::cont` – sees the new tracks right away.
+pub struct KeyframeFile {
+  keyframes: sync::RwLock<Keyframes>,
+  /// Last modification time of the file when it was read.
+  modified: sync::Mutex<Option<SystemTime>>,
+  /// Number of times the file was read again.
+  revision: sync::atomic::AtomicUsize
+}
+
+impl KeyframeFile {
+  /// Read the file at `path`.
+  fn open(path: &str) -> Result<Self, KeyframeError> {
+    let modified = modification_time(path);
+    let keyframes = try!(Keyframes::load(path));
+
+    Ok(KeyframeFile {
+      keyframes: sync::RwLock::new(keyframes),
+      modified: sync::Mutex::new(modified),
+      revision: sync::atomic::AtomicUsize::new(0)
+    })
+  }
+
+  /// Read the file at `path` again and swap the tracks. On failure, the current tracks are left
+  /// untouched.
+  fn refresh(&self, path: &str) -> Result<(), KeyframeError> {
+    let modified = modification_time(path);
+    let keyframes = try!(Keyframes::load(path));
+
+    *self.keyframes.write().unwrap() = keyframes;
+    *self.modified.lock().unwrap() = modified;
+    self.revision.fetch_add(1, sync::atomic::Ordering::SeqCst);
+
+    Ok(())
+  }
+
+  /// Has the file changed on disk since it was last read?
+  fn is_outdated(&self, path: &str) -> bool {
+    let modified = modification_time(path);
+    modified.is_some() && modified != *self.modified.lock().unwrap()
+  }
+
+  /// Number of times the tracks were swapped since the file was opened. Values derived from the
+  /// tracks are outdated when it changes.
+  pub fn revision(&self) -> usize {
+    self.revision.load(sync::atomic::Ordering::SeqCst)
+  }
+
+  /// Current tracks of the file.
+  pub fn keyframes(&self) -> sync::RwLockReadGuard<Keyframes> {
+    self.keyframes.read().unwrap()
+  }
+
+  /// Turn a track of a managed keyframe file into a continuous value. The track is looked up every
+  /// time the value is sampled, so that reloads are taken into account. `def` is used when the
+  /// track can’t be sampled – because it’s not defined at that time, it doesn’t exist or the file
+  /// was unloaded.
+  pub fn cont<'a, T>(file: Managed<KeyframeFile>, name: &str, def: T) -> Cont<'a, T> where T: 'a + TrackValue {
+    let name = name.to_owned();
+    let mut sampler = Sampler::new();
+
+    Cont::new(move |t| {
+      file.upgrade().and_then(|file| {
+        let keyframes = file.keyframes();
+        // random sampling, since the keys might have changed since the last call
+        let value = match keyframes.get(&name) {
+          Some(param) => sampler.sample(t, param, true),
+          // constants and expressions
+          None => keyframes.value(&name, t).and_then(|value| T::from_sample(&TrackSample::F32(value)))
+        };
+
+        value
+      }).unwrap_or(def)
+    })
+  }
+}
+
+impl Resource for KeyframeFile {
+  type Manager = ManagerMap<Self>;
+  type Error = KeyframeError;
+
+  fn load(manager: &mut Self::Manager, name: &str, force: bool) -> Result<Managed<Self>, Self::Error> {
+    // forcing the load of a file that is already managed refreshes it in place instead of
+    // replacing it, so that the handles already given out remain valid
+    if let Some(file) = manager.get(name) {
+      if force {
+        try!(file.refresh(name));
+      }
+
+      return Ok(sync::Arc::downgrade(file));
+    }
+
+    KeyframeFile::open(name).map(|file| {
+      let arc = sync::Arc::new(file);
+      manager.insert(String::from(name), arc.clone());
+      sync::Arc::downgrade(&arc)
+    })
+  }
+
+  fn unload(manager: &mut Self::Manager, name: &str) {
+    default_unload_impl!(manager, name);
+  }
+
+  fn reload(manager: &mut Self::Manager, name: &str) -> Result<(), Self::Error> {
+    Self::load(manager, name, true).map(|_| ())
+  }
+}
+
+/// Reload all the managed keyframe files that changed on disk since they were last read. Files
+/// that fail to reload are left untouched, and their errors are returned.
+pub fn reload_modified(manager: &mut ManagerMap<KeyframeFile>) -> Vec<KeyframeError> {
+  let outdated: Vec<String> = manager.iter().filter(|&(name, file)| file.is_outdated(name)).map(|(name, _)| name.clone()).collect();
+  let mut errors = Vec::new();
+
+  for name in &outdated {
+    info!("reloading keyframes: \x1b[35m{}", name);
+
+    if let Err(e) = KeyframeFile::reload(manager, name) {
+      errors.push(e);
+    }
+  }
+
+  errors
+}
+
+fn modification_time(path: &str) -> Option<SystemTime> {
+  fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// Type of the values of a track.
+#[derive(Copy, Clone, Debug)]
+enum ValueType {
+  F32,
+  Vec2,
+  Vec3,
+  Vec4,
+  Color,
+  Orientation
+}
+
+impl ValueType {
+  fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "f32" => Some(ValueType::F32),
+      "vec2" => Some(ValueType::Vec2),
+      "vec3" => Some(ValueType::Vec3),
+      "vec4" => Some(ValueType::Vec4),
+      "color" => Some(ValueType::Color),
+      "orientation" => Some(ValueType::Orientation),
+      _ => None
+    }
+  }
+
+  // Number of components of a value.
+  fn arity(&self) -> usize {
+    match *self {
+      ValueType::F32 => 1,
+      ValueType::Vec2 => 2,
+      ValueType::Vec3 | ValueType::Color => 3,
+      ValueType::Vec4 | ValueType::Orientation => 4
+    }
+  }
+
+  fn to_track_keys(&self, keys: Vec<Key<Vec<f32>>>) -> TrackKeys {
+    match *self {
+      ValueType::F32 => TrackKeys::F32(typed_keys(keys)),
+      ValueType::Vec2 => TrackKeys::Vec2(typed_keys(keys)),
+      ValueType::Vec3 => TrackKeys::Vec3(typed_keys(keys)),
+      ValueType::Vec4 => TrackKeys::Vec4(typed_keys(keys)),
+      ValueType::Color => TrackKeys::Color(typed_keys(keys)),
+      ValueType::Orientation => TrackKeys::Orientation(typed_keys(keys))
+    }
+  }
+}
+
+// Turn raw keys into typed ones.
+fn typed_keys<T>(keys: Vec<Key<Vec<f32>>>) -> AnimParam<T> where T: TrackValue {
+  let keys = keys.into_iter().map(|key| {
+    Key {
+      t: key.t,
+      value: T::from_components(&key.value),
+      interpolation: key.interpolation,
+      handles: key.handles.map(|(in_handle, out_handle)| {
+        (Handle::new(in_handle.t, T::from_components(&in_handle.value)), Handle::new(out_handle.t, T::from_components(&out_handle.value)))
+      })
+    }
+  }).collect();
+
+  AnimParam::new(keys)
+}
+
+fn extrapolation_from_name(name: &str) -> Option<Extrapolation> {
+  match name {
+    "constant" => Some(Extrapolation::Constant),
+    "linear" => Some(Extrapolation::Linear),
+    "cycle" => Some(Extrapolation::Cycle),
+    "cycle-offset" => Some(Extrapolation::CycleOffset),
+    "ping-pong" => Some(Extrapolation::PingPong),
+    _ => None
+  }
+}
+
+fn extrapolation_name(extrapolation: Extrapolation) -> &'static str {
+  match extrapolation {
+    Extrapolation::Constant => "constant",
+    Extrapolation::Linear => "linear",
+    Extrapolation::Cycle => "cycle",
+    Extrapolation::CycleOffset => "cycle-offset",
+    Extrapolation::PingPong => "ping-pong"
+  }
+}
+
+// Parse the `pre <extrapolation>` and `post <extrapolation>` options of a track line.
+fn parse_extrapolation(tokens: &[(usize, &str)]) -> Result<(Option<Extrapolation>, Option<Extrapolation>), (usize, String)> {
+  let mut pre = None;
+  let mut post = None;
+  let mut i = 0;
+
+  while i < tokens.len() {
+    let (column, keyword) = tokens[i];
+    let slot = match keyword {
+      "pre" => &mut pre,
+      "post" => &mut post,
+      _ => return Err((column, format!("expected pre or post, found {}", keyword)))
+    };
+
+    if slot.is_some() {
+      return Err((column, format!("{} extrapolation is already defined", keyword)));
+    }
+
+    *slot = match tokens.get(i + 1) {
+      Some(&(column, name)) => Some(try!(extrapolation_from_name(name).ok_or_else(|| (column, format!("unknown extrapolation {}", name))))),
+      None => return Err((column, "expected an extrapolation mode".to_owned()))
+    };
+
+    i += 2;
+  }
+
+  Ok((pre, post))
+}
+
+fn interpolation_from_name(name: &str) -> Option<Interpolation> {
+  match name {
+    "hold" => Some(Interpolation::Hold),
+    "linear" => Some(Interpolation::Linear),
+    "cosine" => Some(Interpolation::Cosine),
+    "catmull-rom" => Some(Interpolation::CatmullRom),
+    "bezier" => Some(Interpolation::Bezier),
+    "ease-in-quad" => Some(Interpolation::EaseInQuad),
+    "ease-out-quad" => Some(Interpolation::EaseOutQuad),
+    "ease-in-out-quad" => Some(Interpolation::EaseInOutQuad),
+    "ease-in-cubic" => Some(Interpolation::EaseInCubic),
+    "ease-out-cubic" => Some(Interpolation::EaseOutCubic),
+    "ease-in-out-cubic" => Some(Interpolation::EaseInOutCubic),
+    "ease-in-quart" => Some(Interpolation::EaseInQuart),
+    "ease-out-quart" => Some(Interpolation::EaseOutQuart),
+    "ease-in-out-quart" => Some(Interpolation::EaseInOutQuart),
+    "ease-in-expo" => Some(Interpolation::EaseInExpo),
+    "ease-out-expo" => Some(Interpolation::EaseOutExpo),
+    "ease-in-out-expo" => Some(Interpolation::EaseInOutExpo),
+    "ease-in-back" => Some(Interpolation::EaseInBack),
+    "ease-out-back" => Some(Interpolation::EaseOutBack),
+    "ease-in-out-back" => Some(Interpolation::EaseInOutBack),
+    "ease-in-elastic" => Some(Interpolation::EaseInElastic),
+    "ease-out-elastic" => Some(Interpolation::EaseOutElastic),
+    "ease-in-out-elastic" => Some(Interpolation::EaseInOutElastic),
+    "ease-in-bounce" => Some(Interpolation::EaseInBounce),
+    "ease-out-bounce" => Some(Interpolation::EaseOutBounce),
+    "ease-in-out-bounce" => Some(Interpolation::EaseInOutBounce),
+    _ => None
+  }
+}
+
+fn interpolation_name(interpolation: Interpolation) -> &'static str {
+  match interpolation {
+    Interpolation::Hold => "hold",
+    Interpolation::Linear => "linear",
+    Interpolation::Cosine => "cosine",
+    Interpolation::CatmullRom => "catmull-rom",
+    Interpolation::Bezier => "bezier",
+    Interpolation::EaseInQuad => "ease-in-quad",
+    Interpolation::EaseOutQuad => "ease-out-quad",
+    Interpolation::EaseInOutQuad => "ease-in-out-quad",
+    Interpolation::EaseInCubic => "ease-in-cubic",
+    Interpolation::EaseOutCubic => "ease-out-cubic",
+    Interpolation::EaseInOutCubic => "ease-in-out-cubic",
+    Interpolation::EaseInQuart => "ease-in-quart",
+    Interpolation::EaseOutQuart => "ease-out-quart",
+    Interpolation::EaseInOutQuart => "ease-in-out-quart",
+    Interpolation::EaseInExpo => "ease-in-expo",
+    Interpolation::EaseOutExpo => "ease-out-expo",
+    Interpolation::EaseInOutExpo => "ease-in-out-expo",
+    Interpolation::EaseInBack => "ease-in-back",
+    Interpolation::EaseOutBack => "ease-out-back",
+    Interpolation::EaseInOutBack => "ease-in-out-back",
+    Interpolation::EaseInElastic => "ease-in-elastic",
+    Interpolation::EaseOutElastic => "ease-out-elastic",
+    Interpolation::EaseInOutElastic => "ease-in-out-elastic",
+    Interpolation::EaseInBounce => "ease-in-bounce",
+    Interpolation::EaseOutBounce => "ease-out-bounce",
+    Interpolation::EaseInOutBounce => "ease-in-out-bounce"
+  }
+}
+
+// Split a line into whitespace-separated tokens, along with their column (starting at 1).
+// Comments are dropped.
+fn tokenize(line: &str) -> Vec<(usize, &str)> {
+  let line = line.split('#').next().unwrap_or("");
+  let mut tokens = Vec::new();
+  let mut start = None;
+
+  for (column, (i, c)) in line.char_indices().enumerate() {
+    match (start, c.is_whitespace()) {
+      (None, false) => start = Some((column + 1, i)),
+      (Some((start_column, start_i)), true) => {
+        tokens.push((start_column, &line[start_i..i]));
+        start = None;
+      },
+      _ => {}
+    }
+  }
+
+  if let Some((start_column, start_i)) = start {
+    tokens.push((start_column, &line[start_i..]));
+  }
+
+  tokens
+}
+
+fn parse_number(token: (usize, &str), what: &str) -> Result<f32, (usize, String)> {
+  match token.1.parse::<f32>() {
+    Ok(x) if x.is_finite() => Ok(x),
+    _ => Err((token.0, format!("expected {}, found {}", what, token.1)))
+  }
+}
+
+// Parse `arity` numbers at `i` in `tokens`.
+fn parse_value(tokens: &[(usize, &str)], i: usize, arity: usize) -> Result<Vec<f32>, (usize, String)> {
+  let mut value = Vec::with_capacity(arity);
+
+  for k in 0..arity {
+    match tokens.get(i + k) {
+      Some(&token) => value.push(try!(parse_number(token, "a number"))),
+      None => {
+        let column = tokens.last().map_or(1, |&(column, token)| column + token.chars().count());
+        return Err((column, format!("expected {} components, found {}", arity, k)));
+      }
+    }
+  }
+
+  Ok(value)
+}
+
+// Parse a time, either in seconds or as a musical position if a tempo map is defined.
+fn parse_time(token: (usize, &str), what: &str, tempo: Option<&TempoMap>) -> Result<Time, (usize, String)> {
+  if !token.1.contains(':') {
+    return parse_number(token, what);
+  }
+
+  let tempo = try!(tempo.ok_or_else(|| (token.0, format!("{} in musical time requires a tempo", what))));
+
+  match MusicalTime::parse(token.1) {
+    Some(m) => Ok(tempo.to_seconds(m)),
+    None => Err((token.0, format!("expected {} as bar:beat[:tick], found {}", what, token.1)))
+  }
+}
+
+// Parse a tempo line, `tempo` being the tempo map defined so far.
+fn parse_tempo(tokens: &[(usize, &str)], tempo: Option<TempoMap>) -> Result<TempoMap, (usize, String)> {
+  let bpm = match tokens.get(1) {
+    Some(&token) => try!(parse_number(token, "a tempo")),
+    None => return Err((tokens[0].0, "expected a tempo".to_owned()))
+  };
+
+  if bpm <= 0. {
+    return Err((tokens[1].0, format!("expected a positive tempo, found {}", tokens[1].1)));
+  }
+
+  match tokens.get(2) {
+    Some(&(_, "at")) => {
+      let tempo = try!(tempo.ok_or_else(|| (tokens[0].0, "tempo change without an initial tempo".to_owned())));
+      let at = match tokens.get(3) {
+        Some(&(column, token)) => try!(MusicalTime::parse(token).ok_or_else(|| (column, format!("expected bar:beat[:tick], found {}", token)))),
+        None => return Err((tokens[2].0, "expected a position".to_owned()))
+      };
+
+      if let Some(&(column, token)) = tokens.get(4) {
+        return Err((column, format!("unexpected {}", token)));
+      }
+
+      Ok(tempo.with_tempo_change(at, bpm))
+    },
+    Some(&(column, token)) => {
+      if tempo.is_some() {
+        return Err((tokens[0].0, "tempo is already defined".to_owned()));
+      }
+
+      let signature = try!(parse_signature(token).ok_or_else(|| (column, format!("expected a time signature, found {}", token))));
+      let mut tempo = TempoMap::new(bpm, signature);
+
+      match tokens.get(3) {
+        Some(&(_, "offset")) => {
+          let offset = match tokens.get(4) {
+            Some(&token) => try!(parse_number(token, "an offset")),
+            None => return Err((tokens[3].0, "expected an offset".to_owned()))
+          };
+
+          tempo = tempo.with_offset(offset);
+
+          if let Some(&(column, token)) = tokens.get(5) {
+            return Err((column, format!("unexpected {}", token)));
+          }
+        },
+        Some(&(column, token)) => return Err((column, format!("unexpected {}", token))),
+        None => {}
+      }
+
+      Ok(tempo)
+    },
+    None => Err((tokens[1].0, "expected a time signature or a tempo change".to_owned()))
+  }
+}
+
+// Parse a time signature, such as 4/4.
+fn parse_signature(s: &str) -> Option<TimeSignature> {
+  let mut parts = s.split('/');
+
+  match (parts.next().and_then(|x| x.parse().ok()), parts.next().and_then(|x| x.parse().ok()), parts.next()) {
+    (Some(beats), Some(unit), None) if beats > 0 && unit > 0 => Some(TimeSignature::new(beats, unit)),
+    _ => None
+  }
+}
+
+// Parse a handle introduced by `keyword` at `i` in `tokens`.
+fn parse_handle(tokens: &[(usize, &str)], i: usize, keyword: &str, arity: usize, tempo: Option<&TempoMap>) -> Result<Handle<Vec<f32>>, (usize, String)> {
+  match tokens.get(i) {
+    Some(&(_, token)) if token == keyword => {},
+    Some(&(column, token)) => return Err((column, format!("expected {}, found {}", keyword, token))),
+    None => {
+      let column = tokens.last().map_or(1, |&(column, token)| column + token.chars().count());
+      return Err((column, format!("expected {}", keyword)));
+    }
+  }
+
+  let t = match tokens.get(i + 1) {
+    Some(&token) => try!(parse_time(token, "a handle time", tempo)),
+    None => return Err((tokens[i].0, "expected a handle time".to_owned()))
+  };
+
+  let value = try!(parse_value(tokens, i + 2, arity));
+
+  Ok(Handle::new(t, value))
+}
+
+// Parse a key line of a track which values have `arity` components.
+fn parse_key(tokens: &[(usize, &str)], arity: usize, tempo: Option<&TempoMap>) -> Result<Key<Vec<f32>>, (usize, String)> {
+  let t: Time = try!(parse_time(tokens[0], "a key time", tempo));
+
+  let interpolation = match tokens.get(1) {
+    Some(&(column, name)) => try!(interpolation_from_name(name).ok_or_else(|| (column, format!("unknown interpolation {}", name)))),
+    None => return Err((tokens[0].0, "expected an interpolation mode".to_owned()))
+  };
+
+  let value = try!(parse_value(tokens, 2, arity));
+  let mut i = 2 + arity;
+
+  // any key might have handles: the incoming one of a key shapes the segment before it
+  let handles = if i < tokens.len() {
+    let in_handle = try!(parse_handle(tokens, i, "in", arity, tempo));
+    i += 2 + arity;
+    let out_handle = try!(parse_handle(tokens, i, "out", arity, tempo));
+    i += 2 + arity;
+
+    Some((in_handle, out_handle))
+  } else {
+    None
+  };
+
+  if let Some(&(column, token)) = tokens.get(i) {
+    return Err((column, format!("unexpected {}", token)));
+  }
+
+  Ok(Key {
+    t: t,
+    value: value,
+    interpolation: interpolation,
+    handles: handles
+  })
+}
+
+fn write_components<T>(f: &mut fmt::Formatter, value: &T) -> Result<(), fmt::Error> where T: TrackValue {
+  let mut components = Vec::new();
+  value.to_components(&mut components);
+
+  for c in &components {
+    try!(write!(f, " {}", c));
+  }
+
+  Ok(())
+}
+
+// Write a time as a musical position if there’s a tempo map and the position falls exactly on a
+// tick, in seconds otherwise. Near misses are written in seconds too, since reading their position
+// back would move them.
+fn write_time(f: &mut fmt::Formatter, t: Time, tempo: Option<&TempoMap>) -> Result<(), fmt::Error> {
+  if let Some(tempo) = tempo {
+    let m = tempo.to_musical(t);
+
+    if tempo.to_seconds(m) == t {
+      return write!(f, "{}", m);
+    }
+  }
+
+  write!(f, "{}", t)
+}
+
+fn write_keys<T>(f: &mut fmt::Formatter, param: &AnimParam<T>, tempo: Option<&TempoMap>) -> Result<(), fmt::Error> where T: TrackValue {
+  for key in param {
+    try!(write!(f, "  "));
+    try!(write_time(f, key.t, tempo));
+    try!(write!(f, " {}", interpolation_name(key.interpolation)));
+    try!(write_components(f, &key.value));
+
+    if let Some((ref in_handle, ref out_handle)) = key.handles {
+      try!(write!(f, " in "));
+      try!(write_time(f, in_handle.t, tempo));
+      try!(write_components(f, &in_handle.value));
+      try!(write!(f, " out "));
+      try!(write_time(f, out_handle.t, tempo));
+      try!(write_components(f, &out_handle.value));
+    }
+
+    try!(writeln!(f, ""));
+  }
+
+  Ok(())
+}
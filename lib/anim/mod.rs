@@ -0,0 +1,1729 @@
+use std::f32::consts;
+use std::ops::{Add, Div, Mul, Sub};
+use nalgebra::{Quaternion, UnitQuaternion, Vector2, Vector3, Vector4};
+
+pub mod arc_length;
+pub mod bake;
+pub mod blend;
+pub mod expr;
+pub mod fit;
+pub mod fixed_step;
+pub mod keyframe;
+pub mod spring;
+pub mod tempo;
+pub mod timeline;
+
+pub type Time = f32;
+
+/// Type used to express when keys happen and when parameters are sampled.
+///
+/// `Time` – seconds as `f32` – is the default. `f64` keeps its precision over long timelines and
+/// `i64` counts frames or ticks exactly. Whatever the time type, durations between keys and
+/// interpolation factors are `f32`.
+pub trait TimeType: Copy + PartialOrd {
+  /// Duration from `a` to `b`, in time units.
+  fn delta(a: Self, b: Self) -> f32;
+  /// `t` shifted by `dt` time units. Integer times are rounded to the closest unit.
+  fn shift(t: Self, dt: f32) -> Self;
+}
+
+impl TimeType for f32 {
+  fn delta(a: Self, b: Self) -> f32 {
+    b - a
+  }
+
+  fn shift(t: Self, dt: f32) -> Self {
+    t + dt
+  }
+}
+
+impl TimeType for f64 {
+  fn delta(a: Self, b: Self) -> f32 {
+    (b - a) as f32
+  }
+
+  fn shift(t: Self, dt: f32) -> Self {
+    t + dt as f64
+  }
+}
+
+impl TimeType for i64 {
+  fn delta(a: Self, b: Self) -> f32 {
+    (b - a) as f32
+  }
+
+  fn shift(t: Self, dt: f32) -> Self {
+    t + dt.round() as i64
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Key<T, S = Time> {
+  /// Time at which the `Key` should be reached.
+  pub t: S,
+  /// Actual value.
+  pub value: T,
+  /// Interpolation mode.
+  pub interpolation: Interpolation,
+  /// Incoming and outgoing tangent handles. Only used by the `Interpolation::Bezier` segments
+  /// around the key.
+  pub handles: Option<(Handle<T, S>, Handle<T, S>)>
+}
+
+impl<T, S> Key<T, S> {
+  pub fn new(t: S, value: T, interpolation: Interpolation) -> Self {
+    Key {
+      t: t,
+      value: value,
+      interpolation: interpolation,
+      handles: None
+    }
+  }
+
+  /// Create a `Key` with `Interpolation::Bezier` and its incoming and outgoing tangent handles.
+  pub fn new_bezier(t: S, value: T, in_handle: Handle<T, S>, out_handle: Handle<T, S>) -> Self {
+    Key {
+      t: t,
+      value: value,
+      interpolation: Interpolation::Bezier,
+      handles: Some((in_handle, out_handle))
+    }
+  }
+}
+
+/// Bézier tangent handle.
+///
+/// Handles are expressed in absolute time and value, the same way they’re laid out in curve
+/// editors. The time of a handle is clamped to the segment it shapes, so that the curve cannot go
+/// back in time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Handle<T, S = Time> {
+  /// Time of the handle.
+  pub t: S,
+  /// Value of the handle.
+  pub value: T
+}
+
+impl<T, S> Handle<T, S> {
+  pub fn new(t: S, value: T) -> Self {
+    Handle {
+      t: t,
+      value: value
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interpolation {
+  /// Hold a `Key` until the next one is met.
+  Hold,
+  /// Linear interpolation between a `Key` and the next one.
+  Linear,
+  /// Cosine interpolation between a `Key` and the next one.
+  Cosine,
+  /// Catmull-Rom interpolation.
+  CatmullRom,
+  /// Cubic Bézier interpolation, shaped by the outgoing handle of a `Key` and the incoming handle
+  /// of the next one.
+  Bezier,
+  /// Quadratic ease in: starts slowly and accelerates.
+  EaseInQuad,
+  /// Quadratic ease out: starts fast and decelerates.
+  EaseOutQuad,
+  /// Quadratic ease in and out.
+  EaseInOutQuad,
+  /// Cubic ease in.
+  EaseInCubic,
+  /// Cubic ease out.
+  EaseOutCubic,
+  /// Cubic ease in and out.
+  EaseInOutCubic,
+  /// Quartic ease in.
+  EaseInQuart,
+  /// Quartic ease out.
+  EaseOutQuart,
+  /// Quartic ease in and out.
+  EaseInOutQuart,
+  /// Exponential ease in.
+  EaseInExpo,
+  /// Exponential ease out.
+  EaseOutExpo,
+  /// Exponential ease in and out.
+  EaseInOutExpo,
+  /// Ease in that moves back a bit before leaving the `Key`.
+  EaseInBack,
+  /// Ease out that overshoots the next `Key` before settling on it.
+  EaseOutBack,
+  /// Ease in and out that moves back a bit and overshoots.
+  EaseInOutBack,
+  /// Ease in that oscillates around the `Key` before leaving it.
+  EaseInElastic,
+  /// Ease out that oscillates around the next `Key` before settling on it.
+  EaseOutElastic,
+  /// Ease in and out that oscillates at both ends.
+  EaseInOutElastic,
+  /// Ease in that bounces off the `Key`.
+  EaseInBounce,
+  /// Ease out that bounces on the next `Key`.
+  EaseOutBounce,
+  /// Ease in and out that bounces at both ends.
+  EaseInOutBounce
+}
+
+/// How an `AnimParam` is extended before its first key and after its last one.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Extrapolation {
+  /// Hold the value of the closest key.
+  Constant,
+  /// Keep on going along the line through the two closest keys.
+  Linear,
+  /// Repeat the keys.
+  Cycle,
+  /// Repeat the keys, each cycle starting where the previous one ended.
+  CycleOffset,
+  /// Repeat the keys, playing them backwards every other cycle.
+  PingPong
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimParam<T, S = Time> {
+  control_points: Vec<Key<T, S>>,
+  /// Extrapolation before the first key; `None` leaves the parameter undefined there.
+  pre: Option<Extrapolation>,
+  /// Extrapolation after the last key; `None` leaves the parameter undefined there.
+  post: Option<Extrapolation>
+}
+
+impl<T, S> AnimParam<T, S> where S: TimeType {
+  pub fn new(mut cps: Vec<Key<T, S>>) -> Self {
+    cps.sort_by(|k0, k1| k0.t.partial_cmp(&k1.t).unwrap());
+
+    AnimParam {
+      control_points: cps,
+      pre: None,
+      post: None
+    }
+  }
+
+  /// Set how the parameter is extended before its first key (`pre`) and after its last one
+  /// (`post`).
+  pub fn with_extrapolation(self, pre: Option<Extrapolation>, post: Option<Extrapolation>) -> Self {
+    AnimParam {
+      pre: pre,
+      post: post,
+      ..self
+    }
+  }
+
+  pub fn pre_extrapolation(&self) -> Option<Extrapolation> {
+    self.pre
+  }
+
+  pub fn post_extrapolation(&self) -> Option<Extrapolation> {
+    self.post
+  }
+
+  /// Turn the parameter into a continuous value. `def` is used where the parameter is undefined.
+  pub fn into_cont<'a>(self, def: T) -> Cont<'a, T, S> where T: 'a + Interpolate, S: 'a {
+    let mut sampler = Sampler::new();
+    Cont::from_fn(move |t| sampler.sample(t, &self, true).unwrap_or(def))
+  }
+
+  /// Time of the first and last keys, if any.
+  pub fn time_range(&self) -> Option<(S, S)> {
+    match (self.control_points.first(), self.control_points.last()) {
+      (Some(first), Some(last)) => Some((first.t, last.t)),
+      _ => None
+    }
+  }
+
+  /// Number of keys.
+  pub fn len(&self) -> usize {
+    self.control_points.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.control_points.is_empty()
+  }
+
+  /// All the keys, sorted by time.
+  pub fn keys(&self) -> &[Key<T, S>] {
+    &self.control_points
+  }
+
+  /// Get the key at index `i`.
+  pub fn get(&self, i: usize) -> Option<&Key<T, S>> {
+    self.control_points.get(i)
+  }
+
+  // The editing functions below keep the keys sorted by time; keys are referred to by their
+  // index, which is shifted by insertions, removals and moves. Samplers stay valid after edits.
+
+  /// Insert a key and return its index. A key inserted at the same time than existing keys goes
+  /// after them.
+  pub fn insert(&mut self, key: Key<T, S>) -> usize {
+    let i = self.insertion_index(key.t);
+    self.control_points.insert(i, key);
+    i
+  }
+
+  /// Remove the key at index `i`.
+  pub fn remove(&mut self, i: usize) -> Option<Key<T, S>> {
+    if i < self.control_points.len() {
+      Some(self.control_points.remove(i))
+    } else {
+      None
+    }
+  }
+
+  /// Move the key at index `i` to time `t`, along with its handles. Return the new index of the
+  /// key.
+  pub fn move_key(&mut self, i: usize, t: S) -> Option<usize> {
+    let mut key = match self.remove(i) {
+      Some(key) => key,
+      None => return None
+    };
+
+    let dt = S::delta(key.t, t);
+    key.t = t;
+
+    if let Some((ref mut in_handle, ref mut out_handle)) = key.handles {
+      in_handle.t = S::shift(in_handle.t, dt);
+      out_handle.t = S::shift(out_handle.t, dt);
+    }
+
+    Some(self.insert(key))
+  }
+
+  /// Change the value of the key at index `i`. Its handles are moved along. Return `false` if there
+  /// is no such key.
+  pub fn set_value(&mut self, i: usize, value: T) -> bool where T: Interpolate {
+    match self.control_points.get_mut(i) {
+      Some(key) => {
+        if let Some((ref mut in_handle, ref mut out_handle)) = key.handles {
+          in_handle.value = T::add_delta(in_handle.value, key.value, value, 1.);
+          out_handle.value = T::add_delta(out_handle.value, key.value, value, 1.);
+        }
+
+        key.value = value;
+        true
+      },
+      None => false
+    }
+  }
+
+  /// Change the interpolation mode of the key at index `i`. Return `false` if there is no such
+  /// key.
+  pub fn set_interpolation(&mut self, i: usize, interpolation: Interpolation) -> bool {
+    match self.control_points.get_mut(i) {
+      Some(key) => {
+        key.interpolation = interpolation;
+        true
+      },
+      None => false
+    }
+  }
+
+  /// Change the Bézier handles of the key at index `i`. Return `false` if there is no such key.
+  pub fn set_handles(&mut self, i: usize, handles: Option<(Handle<T, S>, Handle<T, S>)>) -> bool {
+    match self.control_points.get_mut(i) {
+      Some(key) => {
+        key.handles = handles;
+        true
+      },
+      None => false
+    }
+  }
+
+  // Index right after the last key at or before `t`.
+  fn insertion_index(&self, t: S) -> usize {
+    let mut down = 0;
+    let mut up = self.control_points.len();
+
+    while down < up {
+      let m = (down + up) / 2;
+
+      if self.control_points[m].t <= t {
+        down = m + 1;
+      } else {
+        up = m;
+      }
+    }
+
+    down
+  }
+}
+
+pub struct AnimParamIterator<'a, T, S = Time> where T: 'a, S: 'a {
+  anim_param: &'a AnimParam<T, S>,
+  i: usize
+}
+
+impl<'a, T, S> Iterator for AnimParamIterator<'a, T, S> {
+  type Item = &'a Key<T, S>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let r = self.anim_param.control_points.get(self.i);
+
+    if let Some(_) = r {
+      self.i += 1;
+    }
+
+    r
+  }
+}
+
+impl<'a, T, S> IntoIterator for &'a AnimParam<T, S> {
+  type Item = &'a Key<T, S>;
+  type IntoIter = AnimParamIterator<'a, T, S>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    AnimParamIterator {
+      anim_param: self,
+      i: 0
+    }
+  }
+}
+
+/// Implement this trait if your type is a key you want to sample with.
+pub trait Interpolate: Copy {
+  /// Linear interpolation.
+  fn lerp(a: Self, b: Self, t: Time) -> Self;
+  /// Cubic hermite interpolation.
+  fn cubic_hermite(_: (Self, Time), a: (Self, Time), b: (Self, Time), _: (Self, Time), t: Time) -> Self {
+    Self::lerp(a.0, b.0, t)
+  }
+  /// Cubic Bézier interpolation between `a` and `b`, `u` and `v` being the control points.
+  fn cubic_bezier(a: Self, u: Self, v: Self, b: Self, t: Time) -> Self {
+    // de Casteljau
+    let au = Self::lerp(a, u, t);
+    let uv = Self::lerp(u, v, t);
+    let vb = Self::lerp(v, b, t);
+    let auv = Self::lerp(au, uv, t);
+    let uvb = Self::lerp(uv, vb, t);
+
+    Self::lerp(auv, uvb, t)
+  }
+  /// Add `w` times the difference from `a` to `b` to `base`.
+  fn add_delta(base: Self, a: Self, b: Self, w: f32) -> Self {
+    // base + (a + w(b - a)) - a, expressed with lerp only
+    Self::lerp(a, Self::lerp(base, Self::lerp(a, b, w), 0.5), 2.)
+  }
+}
+
+impl Interpolate for f32 {
+  fn lerp(a: Self, b: Self, t: Time) -> Self {
+    lerp(a, b, t)
+  }
+
+  fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite(x, a, b, y, t)
+  }
+  fn add_delta(base: Self, a: Self, b: Self, w: f32) -> Self {
+    add_delta(base, a, b, w)
+  }
+}
+
+impl Interpolate for Vector2<f32> {
+  fn lerp(a: Self, b: Self, t: Time) -> Self {
+    lerp(a, b, t)
+  }
+
+  fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite(x, a, b, y, t)
+  }
+  fn add_delta(base: Self, a: Self, b: Self, w: f32) -> Self {
+    add_delta(base, a, b, w)
+  }
+}
+
+impl Interpolate for Vector3<f32> {
+  fn lerp(a: Self, b: Self, t: Time) -> Self {
+    lerp(a, b, t)
+  }
+
+  fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite(x, a, b, y, t)
+  }
+  fn add_delta(base: Self, a: Self, b: Self, w: f32) -> Self {
+    add_delta(base, a, b, w)
+  }
+}
+
+impl Interpolate for Vector4<f32> {
+  fn lerp(a: Self, b: Self, t: Time) -> Self {
+    lerp(a, b, t)
+  }
+
+  fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite(x, a, b, y, t)
+  }
+  fn add_delta(base: Self, a: Self, b: Self, w: f32) -> Self {
+    add_delta(base, a, b, w)
+  }
+}
+
+impl Interpolate for UnitQuaternion<f32> {
+  fn lerp(a: Self, b: Self, t: Time) -> Self {
+    a * UnitQuaternion::new_with_quaternion((UnitQuaternion::new_with_quaternion(a.quaternion().conjugate()) * b).quaternion().powf(t))
+  }
+
+  fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    UnitQuaternion::new_with_quaternion(squad(*x.0.quaternion(), *a.0.quaternion(), *b.0.quaternion(), *y.0.quaternion(), t))
+  }
+
+  fn add_delta(base: Self, a: Self, b: Self, w: f32) -> Self {
+    // base * (a⁻¹ * b)^w
+    base * UnitQuaternion::new_with_quaternion(a.quaternion().conjugate()) * Self::lerp(a, b, w)
+  }
+}
+
+// Component-wise implementation of Interpolate for arrays.
+macro_rules! impl_interpolate_array {
+  ($($n:expr),*) => {
+    $(
+      impl<T> Interpolate for [T; $n] where T: Interpolate {
+        fn lerp(a: Self, b: Self, t: Time) -> Self {
+          let mut r = a;
+
+          for i in 0..$n {
+            r[i] = T::lerp(a[i], b[i], t);
+          }
+
+          r
+        }
+
+        fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+          let mut r = a.0;
+
+          for i in 0..$n {
+            r[i] = T::cubic_hermite((x.0[i], x.1), (a.0[i], a.1), (b.0[i], b.1), (y.0[i], y.1), t);
+          }
+
+          r
+        }
+
+        fn cubic_bezier(a: Self, u: Self, v: Self, b: Self, t: Time) -> Self {
+          let mut r = a;
+
+          for i in 0..$n {
+            r[i] = T::cubic_bezier(a[i], u[i], v[i], b[i], t);
+          }
+
+          r
+        }
+
+        fn add_delta(base: Self, a: Self, b: Self, w: f32) -> Self {
+          let mut r = base;
+
+          for i in 0..$n {
+            r[i] = T::add_delta(base[i], a[i], b[i], w);
+          }
+
+          r
+        }
+      }
+    )*
+  }
+}
+
+impl_interpolate_array!(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16);
+
+// Component-wise implementation of Interpolate for tuples.
+macro_rules! impl_interpolate_tuple {
+  ($($t:ident: $i:tt),*) => {
+    impl<$($t),*> Interpolate for ($($t,)*) where $($t: Interpolate),* {
+      fn lerp(a: Self, b: Self, t: Time) -> Self {
+        ($($t::lerp(a.$i, b.$i, t),)*)
+      }
+
+      fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+        ($($t::cubic_hermite(((x.0).$i, x.1), ((a.0).$i, a.1), ((b.0).$i, b.1), ((y.0).$i, y.1), t),)*)
+      }
+
+      fn cubic_bezier(a: Self, u: Self, v: Self, b: Self, t: Time) -> Self {
+        ($($t::cubic_bezier(a.$i, u.$i, v.$i, b.$i, t),)*)
+      }
+
+      fn add_delta(base: Self, a: Self, b: Self, w: f32) -> Self {
+        ($($t::add_delta(base.$i, a.$i, b.$i, w),)*)
+      }
+    }
+  }
+}
+
+impl_interpolate_tuple!(A: 0);
+impl_interpolate_tuple!(A: 0, B: 1);
+impl_interpolate_tuple!(A: 0, B: 1, C: 2);
+impl_interpolate_tuple!(A: 0, B: 1, C: 2, D: 3);
+impl_interpolate_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_interpolate_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+
+/// Interpolable types that also support the arithmetic operations of a vector space. Required to
+/// compute derivatives.
+pub trait VectorSpace: Interpolate + Add<Output = Self> + Sub<Output = Self> + Mul<Time, Output = Self> + Div<Time, Output = Self> {}
+
+impl<T> VectorSpace for T where T: Interpolate + Add<Output = T> + Sub<Output = T> + Mul<Time, Output = T> + Div<Time, Output = T> {}
+
+// Default implementation of Interpolate::lerp.
+fn lerp<T>(a: T, b: T, t: Time) -> T where T: Add<Output = T> + Mul<Time, Output = T> {
+  a * (1. - t) + b * t
+}
+
+// Default implementation of Interpolate::add_delta.
+fn add_delta<T>(base: T, a: T, b: T, w: f32) -> T where T: Add<Output = T> + Sub<Output = T> + Mul<Time, Output = T> {
+  base + (b - a) * w
+}
+
+// Default implementation of Interpolate::cubic_hermit.
+
+fn cubic_hermite<T>(x: (T, Time), a: (T, Time), b: (T, Time), y: (T, Time), t: Time) -> T
+    where T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Time, Output = T> + Div<Time, Output = T> {
+  // time stuff
+  let t2 = t * t;
+  let t3 = t2 * t;
+  let two_t3 = 2. * t3;
+  let three_t2 = 3. * t2;
+
+  // tangents
+  let m0 = (b.0 - x.0) / (b.1 - x.1);
+	let m1 = (y.0 - a.0) / (y.1 - a.1);
+
+  a.0 * (two_t3 - three_t2 + 1.) + m0 * (t3 - 2. * t2 + t) + b.0 * (-two_t3 + three_t2) + m1 * (t3 - t2)
+}
+
+// First and second derivatives of cubic_hermite with respect to `t`.
+fn cubic_hermite_derivatives<T>(x: (T, Time), a: (T, Time), b: (T, Time), y: (T, Time), t: Time) -> (T, T) where T: VectorSpace {
+  let t2 = t * t;
+
+  // tangents
+  let m0 = (b.0 - x.0) / (b.1 - x.1);
+  let m1 = (y.0 - a.0) / (y.1 - a.1);
+
+  let d = a.0 * (6. * t2 - 6. * t) + m0 * (3. * t2 - 4. * t + 1.) + b.0 * (-6. * t2 + 6. * t) + m1 * (3. * t2 - 2. * t);
+  let d2 = a.0 * (12. * t - 6.) + m0 * (6. * t - 4.) + b.0 * (-12. * t + 6.) + m1 * (6. * t - 2.);
+
+  (d, d2)
+}
+
+// Spherical cubic interpolation (squad) between `a` and `b`, `x` and `y` being the orientations
+// surrounding them. Each orientation is first flipped to lie in the same hemisphere as the one
+// before it, so that the curve always takes the shortest path.
+fn squad(x: Quaternion<f32>, a: Quaternion<f32>, b: Quaternion<f32>, y: Quaternion<f32>, t: Time) -> Quaternion<f32> {
+  let x = quat_same_hemisphere(a, x);
+  let b = quat_same_hemisphere(a, b);
+  let y = quat_same_hemisphere(b, y);
+
+  let sa = squad_inner(x, a, b);
+  let sb = squad_inner(a, b, y);
+
+  quat_slerp(quat_slerp(a, b, t), quat_slerp(sa, sb, t), 2. * t * (1. - t))
+}
+
+// Inner control point of squad for `q`, given its previous and next orientations.
+fn squad_inner(prev: Quaternion<f32>, q: Quaternion<f32>, next: Quaternion<f32>) -> Quaternion<f32> {
+  let q_inv = q.conjugate();
+  let l0 = quat_log(quat_mul(q_inv, next));
+  let l1 = quat_log(quat_mul(q_inv, prev));
+  let e = Quaternion::new(0., -(l0.i + l1.i) * 0.25, -(l0.j + l1.j) * 0.25, -(l0.k + l1.k) * 0.25);
+
+  quat_mul(q, quat_exp(e))
+}
+
+// Negate `q` if it lies in the opposite hemisphere of `reference`. Both represent the same
+// orientation.
+fn quat_same_hemisphere(reference: Quaternion<f32>, q: Quaternion<f32>) -> Quaternion<f32> {
+  if quat_dot(reference, q) < 0. {
+    Quaternion::new(-q.w, -q.i, -q.j, -q.k)
+  } else {
+    q
+  }
+}
+
+fn quat_dot(a: Quaternion<f32>, b: Quaternion<f32>) -> f32 {
+  a.w * b.w + a.i * b.i + a.j * b.j + a.k * b.k
+}
+
+fn quat_mul(a: Quaternion<f32>, b: Quaternion<f32>) -> Quaternion<f32> {
+  Quaternion::new(a.w * b.w - a.i * b.i - a.j * b.j - a.k * b.k,
+                  a.w * b.i + a.i * b.w + a.j * b.k - a.k * b.j,
+                  a.w * b.j - a.i * b.k + a.j * b.w + a.k * b.i,
+                  a.w * b.k + a.i * b.j - a.j * b.i + a.k * b.w)
+}
+
+// Logarithm of a unit quaternion; the result is a pure quaternion.
+fn quat_log(q: Quaternion<f32>) -> Quaternion<f32> {
+  let vn = (q.i * q.i + q.j * q.j + q.k * q.k).sqrt();
+
+  if vn <= 1e-6 {
+    return Quaternion::new(0., 0., 0., 0.);
+  }
+
+  let k = f32::atan2(vn, q.w) / vn;
+  Quaternion::new(0., q.i * k, q.j * k, q.k * k)
+}
+
+// Exponential of a pure quaternion; the result is a unit quaternion.
+fn quat_exp(q: Quaternion<f32>) -> Quaternion<f32> {
+  let vn = (q.i * q.i + q.j * q.j + q.k * q.k).sqrt();
+
+  if vn <= 1e-6 {
+    return Quaternion::new(1., q.i, q.j, q.k);
+  }
+
+  let k = vn.sin() / vn;
+  Quaternion::new(vn.cos(), q.i * k, q.j * k, q.k * k)
+}
+
+// Spherical linear interpolation, without any hemisphere correction.
+fn quat_slerp(a: Quaternion<f32>, b: Quaternion<f32>, t: Time) -> Quaternion<f32> {
+  let d = quat_dot(a, b).max(-1.).min(1.);
+
+  // fall back to linear interpolation when both quaternions are too close
+  let (ka, kb) = if d.abs() > 0.9995 {
+    (1. - t, t)
+  } else {
+    let theta = d.acos();
+    let sin_theta = theta.sin();
+    (((1. - t) * theta).sin() / sin_theta, (t * theta).sin() / sin_theta)
+  };
+
+  Quaternion::new(a.w * ka + b.w * kb, a.i * ka + b.i * kb, a.j * ka + b.j * kb, a.k * ka + b.k * kb)
+}
+
+/// Samplers can sample `AnimParam` by providing a time. They should be mutable so that they can
+/// maintain an internal state for optimization purposes.
+pub struct Sampler {
+  /// Playback cursor – gives the lower control point index of the current portion of the curve
+  /// we’re sampling at.
+  cursor: usize
+}
+
+impl Sampler {
+  pub fn new() -> Self {
+    Sampler {
+      cursor: 0
+    }
+  }
+
+  /// Sample an animation `param` at `t`. If `random_sampling` is set, random sampling is generally
+	/// faster than continuous sampling. Though, if you use continuous sampling, set `random_sampling`
+	/// to `false` for max speed performance.
+  ///
+  /// Outside of the keys, the parameter is extrapolated as set with
+  /// `AnimParam::with_extrapolation`; if it’s not, `None` is returned.
+  pub fn sample<T, S>(&mut self, t: S, param: &AnimParam<T, S>, random_sampling: bool) -> Option<T>
+      where T: Interpolate, S: TimeType {
+    if let Some(value) = self.interpolate(t, param, random_sampling) {
+      return Some(value);
+    }
+
+    let cps = &param.control_points;
+
+    match extrapolate_time(param, t) {
+      Some(Extrapolated::Key(i)) => Some(cps[i].value),
+      Some(Extrapolated::Line(i, j)) => Some(Interpolate::lerp(cps[i].value, cps[j].value, normalize_time(t, &cps[i], &cps[j]))),
+      Some(Extrapolated::Folded(ft, cycles, _)) => {
+        let first = &cps[0];
+        let last = &cps[cps.len() - 1];
+        let value = self.interpolate(ft, param, random_sampling).unwrap_or(last.value);
+
+        if cycles == 0 {
+          Some(value)
+        } else {
+          Some(Interpolate::add_delta(value, first.value, last.value, cycles as f32))
+        }
+      },
+      None => None
+    }
+  }
+
+  // Sample `param` at `t` if `t` lies within its keys.
+  fn interpolate<T, S>(&mut self, t: S, param: &AnimParam<T, S>, random_sampling: bool) -> Option<T>
+      where T: Interpolate, S: TimeType {
+    let i = match self.lower_cp(t, param, random_sampling) {
+      Some(i) => i,
+      None => return None
+    };
+
+    let cp0 = &param.control_points[i];
+
+    match cp0.interpolation {
+      Interpolation::Hold => Some(cp0.value),
+      Interpolation::Linear => {
+        let cp1 = &param.control_points[i+1];
+        let nt = normalize_time(t, cp0, cp1);
+
+        Some(Interpolate::lerp(cp0.value, cp1.value, nt))
+      },
+      Interpolation::Cosine => {
+        let cp1 = &param.control_points[i+1];
+        let nt = normalize_time(t, cp0, cp1);
+        let cos_nt = (1. - f32::cos(nt * consts::PI)) * 0.5;
+
+        Some(Interpolate::lerp(cp0.value, cp1.value, cos_nt))
+      },
+      Interpolation::EaseInQuad | Interpolation::EaseOutQuad | Interpolation::EaseInOutQuad |
+      Interpolation::EaseInCubic | Interpolation::EaseOutCubic | Interpolation::EaseInOutCubic |
+      Interpolation::EaseInQuart | Interpolation::EaseOutQuart | Interpolation::EaseInOutQuart |
+      Interpolation::EaseInExpo | Interpolation::EaseOutExpo | Interpolation::EaseInOutExpo |
+      Interpolation::EaseInBack | Interpolation::EaseOutBack | Interpolation::EaseInOutBack |
+      Interpolation::EaseInElastic | Interpolation::EaseOutElastic | Interpolation::EaseInOutElastic |
+      Interpolation::EaseInBounce | Interpolation::EaseOutBounce | Interpolation::EaseInOutBounce => {
+        let cp1 = &param.control_points[i+1];
+        let nt = normalize_time(t, cp0, cp1);
+
+        Some(Interpolate::lerp(cp0.value, cp1.value, ease(cp0.interpolation, nt)))
+      },
+      Interpolation::CatmullRom => {
+        let cp1 = &param.control_points[i+1];
+        let (cpm0, cpm1) = catmull_rom_neighbors(&param.control_points, i);
+        let nt = normalize_time(t, cp0, cp1);
+
+        Some(Interpolate::cubic_hermite(cpm0, (cp0.value, 0.), (cp1.value, S::delta(cp0.t, cp1.t)), cpm1, nt))
+      },
+      Interpolation::Bezier => {
+        let cp1 = &param.control_points[i+1];
+        let (h0, h1) = bezier_handles(cp0, cp1);
+        let u = bezier_solve_time(S::delta(cp0.t, t), 0., S::delta(cp0.t, h0.t), S::delta(cp0.t, h1.t), S::delta(cp0.t, cp1.t));
+
+        Some(Interpolate::cubic_bezier(cp0.value, h0.value, h1.value, cp1.value, u))
+      }
+    }
+  }
+
+  /// Sample the first derivative of an animation `param` at `t` – i.e. its speed, in value per
+  /// time unit. See `sample` for `random_sampling`.
+  pub fn sample_derivative<T, S>(&mut self, t: S, param: &AnimParam<T, S>, random_sampling: bool) -> Option<T>
+      where T: VectorSpace, S: TimeType {
+    self.sample_derivatives(t, param, random_sampling).map(|(d, _)| d)
+  }
+
+  /// Sample the second derivative of an animation `param` at `t` – i.e. its acceleration. See
+  /// `sample` for `random_sampling`.
+  pub fn sample_second_derivative<T, S>(&mut self, t: S, param: &AnimParam<T, S>, random_sampling: bool) -> Option<T>
+      where T: VectorSpace, S: TimeType {
+    self.sample_derivatives(t, param, random_sampling).map(|(_, d2)| d2)
+  }
+
+  // Sample both the first and second derivatives, extrapolating if needed.
+  fn sample_derivatives<T, S>(&mut self, t: S, param: &AnimParam<T, S>, random_sampling: bool) -> Option<(T, T)>
+      where T: VectorSpace, S: TimeType {
+    if let Some(d) = self.interpolate_derivatives(t, param, random_sampling) {
+      return Some(d);
+    }
+
+    let cps = &param.control_points;
+
+    match extrapolate_time(param, t) {
+      Some(Extrapolated::Key(i)) => {
+        let zero = cps[i].value - cps[i].value;
+        Some((zero, zero))
+      },
+      Some(Extrapolated::Line(i, j)) => {
+        let delta = cps[j].value - cps[i].value;
+        Some((delta / S::delta(cps[i].t, cps[j].t), delta - delta))
+      },
+      Some(Extrapolated::Folded(ft, _, reversed)) => {
+        self.interpolate_derivatives(ft, param, random_sampling).map(|(d, d2)| {
+          if reversed { (d * -1., d2) } else { (d, d2) }
+        })
+      },
+      None => None
+    }
+  }
+
+  // Sample both the first and second derivatives if `t` lies within the keys of `param`.
+  fn interpolate_derivatives<T, S>(&mut self, t: S, param: &AnimParam<T, S>, random_sampling: bool) -> Option<(T, T)>
+      where T: VectorSpace, S: TimeType {
+    let i = match self.lower_cp(t, param, random_sampling) {
+      Some(i) => i,
+      None => return None
+    };
+
+    let cp0 = &param.control_points[i];
+    let zero = cp0.value - cp0.value;
+
+    if let Interpolation::Hold = cp0.interpolation {
+      return Some((zero, zero));
+    }
+
+    let cp1 = &param.control_points[i+1];
+    let dur = S::delta(cp0.t, cp1.t);
+    let nt = normalize_time(t, cp0, cp1);
+    let delta = cp1.value - cp0.value;
+
+    match cp0.interpolation {
+      Interpolation::Linear => Some((delta / dur, zero)),
+      Interpolation::Cosine => {
+        let d = consts::PI * 0.5 * f32::sin(nt * consts::PI);
+        let d2 = consts::PI * consts::PI * 0.5 * f32::cos(nt * consts::PI);
+
+        Some((delta * (d / dur), delta * (d2 / (dur * dur))))
+      },
+      Interpolation::CatmullRom => {
+        let (cpm0, cpm1) = catmull_rom_neighbors(&param.control_points, i);
+        let (d, d2) = cubic_hermite_derivatives(cpm0, (cp0.value, 0.), (cp1.value, dur), cpm1, nt);
+
+        Some((d / dur, d2 / (dur * dur)))
+      },
+      Interpolation::Bezier => {
+        let (h0, h1) = bezier_handles(cp0, cp1);
+        let (x1, x2) = (S::delta(cp0.t, h0.t), S::delta(cp0.t, h1.t));
+        let u = bezier_solve_time(S::delta(cp0.t, t), 0., x1, x2, dur);
+        let (dx, dx2) = cubic_bezier_derivatives(0., x1, x2, dur, u);
+        let (dy, dy2) = cubic_bezier_derivatives(cp0.value, h0.value, h1.value, cp1.value, u);
+        // the time curve is flat where a handle lies on its key; avoid dividing by zero there
+        let dx = if dx.abs() < 1e-6 { 1e-6 } else { dx };
+
+        Some((dy / dx, (dy2 * dx - dy * dx2) / (dx * dx * dx)))
+      },
+      easing => {
+        let (d, d2) = ease_derivatives(easing, nt);
+        Some((delta * (d / dur), delta * (d2 / (dur * dur))))
+      }
+    }
+  }
+
+  // Find the lower control point of the portion of the curve `t` lies in.
+  fn lower_cp<T, S>(&mut self, t: S, param: &AnimParam<T, S>, random_sampling: bool) -> Option<usize> where S: TimeType {
+    if random_sampling {
+      binary_search_lower_cp(&param.control_points, t)
+    } else {
+      let i = around_search_lower_cp(&param.control_points, self.cursor, t);
+
+      // if we’ve found the index, replace the cursor to speed up next searches
+      if let Some(cursor) = i {
+        self.cursor = cursor;
+      }
+
+      i
+    }
+  }
+}
+
+// Overshoot of back easing curves.
+const BACK: Time = 1.70158;
+const BACK_IN_OUT: Time = BACK * 1.525;
+// Pulsations of elastic easing curves.
+const ELASTIC: Time = 2. * consts::PI / 3.;
+const ELASTIC_IN_OUT: Time = 2. * consts::PI / 4.5;
+
+// Easing curve of an easing interpolation mode, mapping a normalized time to an interpolation
+// factor. Curves are the usual ones (see https://easings.net).
+fn ease(interpolation: Interpolation, t: Time) -> Time {
+  match interpolation {
+    Interpolation::EaseInQuad => t * t,
+    Interpolation::EaseOutQuad => 1. - (1. - t).powi(2),
+    Interpolation::EaseInOutQuad => if t < 0.5 { 2. * t * t } else { 1. - (2. - 2. * t).powi(2) * 0.5 },
+    Interpolation::EaseInCubic => t * t * t,
+    Interpolation::EaseOutCubic => 1. - (1. - t).powi(3),
+    Interpolation::EaseInOutCubic => if t < 0.5 { 4. * t * t * t } else { 1. - (2. - 2. * t).powi(3) * 0.5 },
+    Interpolation::EaseInQuart => t.powi(4),
+    Interpolation::EaseOutQuart => 1. - (1. - t).powi(4),
+    Interpolation::EaseInOutQuart => if t < 0.5 { 8. * t.powi(4) } else { 1. - (2. - 2. * t).powi(4) * 0.5 },
+    Interpolation::EaseInExpo => if t <= 0. { 0. } else { f32::powf(2., 10. * t - 10.) },
+    Interpolation::EaseOutExpo => if t >= 1. { 1. } else { 1. - f32::powf(2., -10. * t) },
+    Interpolation::EaseInOutExpo => {
+      if t <= 0. {
+        0.
+      } else if t >= 1. {
+        1.
+      } else if t < 0.5 {
+        f32::powf(2., 20. * t - 10.) * 0.5
+      } else {
+        1. - f32::powf(2., 10. - 20. * t) * 0.5
+      }
+    },
+    Interpolation::EaseInBack => (BACK + 1.) * t * t * t - BACK * t * t,
+    Interpolation::EaseOutBack => 1. + (BACK + 1.) * (t - 1.).powi(3) + BACK * (t - 1.).powi(2),
+    Interpolation::EaseInOutBack => {
+      if t < 0.5 {
+        (2. * t).powi(2) * ((BACK_IN_OUT + 1.) * 2. * t - BACK_IN_OUT) * 0.5
+      } else {
+        ((2. * t - 2.).powi(2) * ((BACK_IN_OUT + 1.) * (2. * t - 2.) + BACK_IN_OUT) + 2.) * 0.5
+      }
+    },
+    Interpolation::EaseInElastic => {
+      if t <= 0. {
+        0.
+      } else if t >= 1. {
+        1.
+      } else {
+        -f32::powf(2., 10. * t - 10.) * ((10. * t - 10.75) * ELASTIC).sin()
+      }
+    },
+    Interpolation::EaseOutElastic => {
+      if t <= 0. {
+        0.
+      } else if t >= 1. {
+        1.
+      } else {
+        f32::powf(2., -10. * t) * ((10. * t - 0.75) * ELASTIC).sin() + 1.
+      }
+    },
+    Interpolation::EaseInOutElastic => {
+      if t <= 0. {
+        0.
+      } else if t >= 1. {
+        1.
+      } else if t < 0.5 {
+        -f32::powf(2., 20. * t - 10.) * ((20. * t - 11.125) * ELASTIC_IN_OUT).sin() * 0.5
+      } else {
+        f32::powf(2., 10. - 20. * t) * ((20. * t - 11.125) * ELASTIC_IN_OUT).sin() * 0.5 + 1.
+      }
+    },
+    Interpolation::EaseInBounce => 1. - ease_out_bounce(1. - t),
+    Interpolation::EaseOutBounce => ease_out_bounce(t),
+    Interpolation::EaseInOutBounce => {
+      if t < 0.5 {
+        (1. - ease_out_bounce(1. - 2. * t)) * 0.5
+      } else {
+        (1. + ease_out_bounce(2. * t - 1.)) * 0.5
+      }
+    },
+    _ => t
+  }
+}
+
+// First and second derivatives of the easing curve of an easing interpolation mode (see `ease`),
+// computed analytically.
+fn ease_derivatives(interpolation: Interpolation, t: Time) -> (Time, Time) {
+  match interpolation {
+    Interpolation::EaseInQuad => (2. * t, 2.),
+    Interpolation::EaseOutQuad => (2. * (1. - t), -2.),
+    Interpolation::EaseInOutQuad => if t < 0.5 { (4. * t, 4.) } else { (4. * (1. - t), -4.) },
+    Interpolation::EaseInCubic => (3. * t * t, 6. * t),
+    Interpolation::EaseOutCubic => (3. * (1. - t).powi(2), -6. * (1. - t)),
+    Interpolation::EaseInOutCubic => {
+      if t < 0.5 {
+        (12. * t * t, 24. * t)
+      } else {
+        (3. * (2. - 2. * t).powi(2), -12. * (2. - 2. * t))
+      }
+    },
+    Interpolation::EaseInQuart => (4. * t.powi(3), 12. * t * t),
+    Interpolation::EaseOutQuart => (4. * (1. - t).powi(3), -12. * (1. - t).powi(2)),
+    Interpolation::EaseInOutQuart => {
+      if t < 0.5 {
+        (32. * t.powi(3), 96. * t * t)
+      } else {
+        (4. * (2. - 2. * t).powi(3), -24. * (2. - 2. * t).powi(2))
+      }
+    },
+    Interpolation::EaseInExpo => {
+      if t <= 0. {
+        (0., 0.)
+      } else {
+        let k = 10. * consts::LN_2;
+        let e = f32::powf(2., 10. * t - 10.);
+        (k * e, k * k * e)
+      }
+    },
+    Interpolation::EaseOutExpo => {
+      if t >= 1. {
+        (0., 0.)
+      } else {
+        let k = 10. * consts::LN_2;
+        let e = f32::powf(2., -10. * t);
+        (k * e, -k * k * e)
+      }
+    },
+    Interpolation::EaseInOutExpo => {
+      let k = 20. * consts::LN_2;
+
+      if t <= 0. || t >= 1. {
+        (0., 0.)
+      } else if t < 0.5 {
+        let e = f32::powf(2., 20. * t - 10.) * 0.5;
+        (k * e, k * k * e)
+      } else {
+        let e = f32::powf(2., 10. - 20. * t) * 0.5;
+        (k * e, -k * k * e)
+      }
+    },
+    Interpolation::EaseInBack => (3. * (BACK + 1.) * t * t - 2. * BACK * t, 6. * (BACK + 1.) * t - 2. * BACK),
+    Interpolation::EaseOutBack => {
+      let u = t - 1.;
+      (3. * (BACK + 1.) * u * u + 2. * BACK * u, 6. * (BACK + 1.) * u + 2. * BACK)
+    },
+    Interpolation::EaseInOutBack => {
+      if t < 0.5 {
+        let x = 2. * t;
+        (3. * (BACK_IN_OUT + 1.) * x * x - 2. * BACK_IN_OUT * x, 2. * (6. * (BACK_IN_OUT + 1.) * x - 2. * BACK_IN_OUT))
+      } else {
+        let x = 2. * t - 2.;
+        (3. * (BACK_IN_OUT + 1.) * x * x + 2. * BACK_IN_OUT * x, 2. * (6. * (BACK_IN_OUT + 1.) * x + 2. * BACK_IN_OUT))
+      }
+    },
+    // elastic curves are an exponential envelope a times a sine of pulsation w: (a·sin)' and
+    // (a·sin)'' only need a' = ±k·a
+    Interpolation::EaseInElastic => {
+      if t <= 0. || t >= 1. {
+        (0., 0.)
+      } else {
+        let (k, w) = (10. * consts::LN_2, 10. * ELASTIC);
+        let a = f32::powf(2., 10. * t - 10.);
+        let (s, c) = ((10. * t - 10.75) * ELASTIC).sin_cos();
+        (-a * (k * s + w * c), -a * ((k * k - w * w) * s + 2. * k * w * c))
+      }
+    },
+    Interpolation::EaseOutElastic => {
+      if t <= 0. || t >= 1. {
+        (0., 0.)
+      } else {
+        let (k, w) = (10. * consts::LN_2, 10. * ELASTIC);
+        let a = f32::powf(2., -10. * t);
+        let (s, c) = ((10. * t - 0.75) * ELASTIC).sin_cos();
+        (a * (w * c - k * s), a * ((k * k - w * w) * s - 2. * k * w * c))
+      }
+    },
+    Interpolation::EaseInOutElastic => {
+      let (k, w) = (20. * consts::LN_2, 20. * ELASTIC_IN_OUT);
+      let (s, c) = ((20. * t - 11.125) * ELASTIC_IN_OUT).sin_cos();
+
+      if t <= 0. || t >= 1. {
+        (0., 0.)
+      } else if t < 0.5 {
+        let a = f32::powf(2., 20. * t - 10.) * 0.5;
+        (-a * (k * s + w * c), -a * ((k * k - w * w) * s + 2. * k * w * c))
+      } else {
+        let a = f32::powf(2., 10. - 20. * t) * 0.5;
+        (a * (w * c - k * s), a * ((k * k - w * w) * s - 2. * k * w * c))
+      }
+    },
+    Interpolation::EaseInBounce => {
+      let (d, d2) = ease_out_bounce_derivatives(1. - t);
+      (d, -d2)
+    },
+    Interpolation::EaseOutBounce => ease_out_bounce_derivatives(t),
+    Interpolation::EaseInOutBounce => {
+      if t < 0.5 {
+        let (d, d2) = ease_out_bounce_derivatives(1. - 2. * t);
+        (d, -2. * d2)
+      } else {
+        let (d, d2) = ease_out_bounce_derivatives(2. * t - 1.);
+        (d, 2. * d2)
+      }
+    },
+    _ => (1., 0.)
+  }
+}
+
+const BOUNCE_N: Time = 7.5625;
+
+// Bounces are parabolas: the one `t` lies on is `BOUNCE_N * (t - center)² + height`. Return its
+// center and height.
+fn bounce_at(t: Time) -> (Time, Time) {
+  const D: Time = 2.75;
+
+  if t < 1. / D {
+    (0., 0.)
+  } else if t < 2. / D {
+    (1.5 / D, 0.75)
+  } else if t < 2.5 / D {
+    (2.25 / D, 0.9375)
+  } else {
+    (2.625 / D, 0.984375)
+  }
+}
+
+fn ease_out_bounce(t: Time) -> Time {
+  let (center, height) = bounce_at(t);
+  BOUNCE_N * (t - center).powi(2) + height
+}
+
+fn ease_out_bounce_derivatives(t: Time) -> (Time, Time) {
+  let (center, _) = bounce_at(t);
+  (2. * BOUNCE_N * (t - center), 2. * BOUNCE_N)
+}
+
+// Get the control points surrounding the segment starting at `i`, as required by Catmull-Rom. On
+// the first and last segments, the missing control point is a phantom one, obtained by reflecting
+// the other end of the segment. Times are relative to the start of the segment.
+fn catmull_rom_neighbors<T, S>(cps: &Vec<Key<T, S>>, i: usize) -> ((T, Time), (T, Time))
+    where T: Interpolate, S: TimeType {
+  let cp0 = &cps[i];
+  let cp1 = &cps[i+1];
+  let dur = S::delta(cp0.t, cp1.t);
+
+  let before = if i == 0 {
+    (T::lerp(cp1.value, cp0.value, 2.), -dur)
+  } else {
+    (cps[i-1].value, S::delta(cp0.t, cps[i-1].t))
+  };
+
+  let after = if i + 2 >= cps.len() {
+    (T::lerp(cp0.value, cp1.value, 2.), 2. * dur)
+  } else {
+    (cps[i+2].value, S::delta(cp0.t, cps[i+2].t))
+  };
+
+  (before, after)
+}
+
+// Get the outgoing handle of `cp0` and the incoming handle of `cp1`, with their time clamped to
+// the segment. A missing handle collapses onto its key.
+fn bezier_handles<T, S>(cp0: &Key<T, S>, cp1: &Key<T, S>) -> (Handle<T, S>, Handle<T, S>) where T: Copy, S: TimeType {
+  let h0 = cp0.handles.map_or(Handle::new(cp0.t, cp0.value), |(_, out_handle)| out_handle);
+  let h1 = cp1.handles.map_or(Handle::new(cp1.t, cp1.value), |(in_handle, _)| in_handle);
+
+  (Handle::new(clamp_time(h0.t, cp0.t, cp1.t), h0.value), Handle::new(clamp_time(h1.t, cp0.t, cp1.t), h1.value))
+}
+
+// Clamp a time to `[t0; t1]`.
+fn clamp_time<S>(t: S, t0: S, t1: S) -> S where S: TimeType {
+  if t < t0 {
+    t0
+  } else if t > t1 {
+    t1
+  } else {
+    t
+  }
+}
+
+// Evaluate a 1D cubic Bézier curve.
+fn bezier_1d(x0: Time, x1: Time, x2: Time, x3: Time, u: Time) -> Time {
+  let v = 1. - u;
+
+  x0 * v * v * v + 3. * x1 * v * v * u + 3. * x2 * v * u * u + x3 * u * u * u
+}
+
+// First and second derivatives of a cubic Bézier curve with respect to its parameter `u`.
+fn cubic_bezier_derivatives<T>(a: T, p: T, q: T, b: T, u: Time) -> (T, T) where T: VectorSpace {
+  let v = 1. - u;
+  let d = ((p - a) * (v * v) + (q - p) * (2. * u * v) + (b - q) * (u * u)) * 3.;
+  let d2 = ((q - p - (p - a)) * v + (b - q - (q - p)) * u) * 6.;
+
+  (d, d2)
+}
+
+// Find the Bézier parameter at which the time curve reaches `t`. The time curve is monotonic
+// because the handles are clamped to the segment, so a bisection always converges.
+fn bezier_solve_time(t: Time, x0: Time, x1: Time, x2: Time, x3: Time) -> Time {
+  let mut down = 0.;
+  let mut up = 1.;
+  let mut u = normalize_time_range(t, x0, x3);
+
+  for _ in 0..32 {
+    let x = bezier_1d(x0, x1, x2, x3, u);
+
+    if (x - t).abs() <= 1e-6 {
+      break;
+    }
+
+    if x < t {
+      down = u;
+    } else {
+      up = u;
+    }
+
+    u = (down + up) * 0.5;
+  }
+
+  u
+}
+
+// Where a time outside of the keys of a parameter lands once extrapolated.
+enum Extrapolated<S> {
+  // the value of a key
+  Key(usize),
+  // the line through two keys
+  Line(usize, usize),
+  // a time within the keys, the number of cycles to offset the value by and whether the keys are
+  // played backwards
+  Folded(S, i32, bool)
+}
+
+// Extrapolate `t`, which must lie outside of the keys of `param`.
+fn extrapolate_time<T, S>(param: &AnimParam<T, S>, t: S) -> Option<Extrapolated<S>> where S: TimeType {
+  let cps = &param.control_points;
+
+  if cps.is_empty() {
+    return None;
+  }
+
+  let last = cps.len() - 1;
+  let before = t < cps[0].t;
+  let mode = if before { param.pre } else { param.post };
+  let mode = match mode {
+    Some(mode) => mode,
+    None => return None
+  };
+
+  let t0 = cps[0].t;
+  let t1 = cps[last].t;
+  let dur = S::delta(t0, t1);
+
+  // a single key – or keys all at the same time – can only be held
+  if dur <= 0. {
+    return Some(Extrapolated::Key(if before { 0 } else { last }));
+  }
+
+  match mode {
+    Extrapolation::Constant => Some(Extrapolated::Key(if before { 0 } else { last })),
+    Extrapolation::Linear => {
+      let (i, j) = if before { (0, 1) } else { (last - 1, last) };
+
+      // two keys at the same time give no slope: hold the outer one instead
+      if S::delta(cps[i].t, cps[j].t) <= 0. {
+        Some(Extrapolated::Key(if before { i } else { j }))
+      } else {
+        Some(Extrapolated::Line(i, j))
+      }
+    },
+    Extrapolation::Cycle | Extrapolation::CycleOffset | Extrapolation::PingPong => {
+      let cycles = f32::floor(S::delta(t0, t) / dur);
+      let local = modulo(S::delta(t0, t), dur);
+
+      match mode {
+        Extrapolation::Cycle => Some(Extrapolated::Folded(S::shift(t0, local), 0, false)),
+        Extrapolation::CycleOffset => Some(Extrapolated::Folded(S::shift(t0, local), cycles as i32, false)),
+        _ => {
+          if modulo(cycles, 2.) == 0. {
+            Some(Extrapolated::Folded(S::shift(t0, local), 0, false))
+          } else {
+            Some(Extrapolated::Folded(S::shift(t1, -local), 0, true))
+          }
+        }
+      }
+    }
+  }
+}
+
+// Normalize a time ([0;1]) given two control points.
+fn normalize_time<T, S>(t: S, cp: &Key<T, S>, cp1: &Key<T, S>) -> Time where S: TimeType {
+  S::delta(cp.t, t) / S::delta(cp.t, cp1.t)
+}
+
+// Normalize a time ([0;1]) given two bounds.
+fn normalize_time_range(t: Time, t0: Time, t1: Time) -> Time {
+  (t - t0) / (t1 - t0)
+}
+
+// Find the lower control point corresponding to a given time. Random version.
+fn binary_search_lower_cp<T, S>(cps: &Vec<Key<T, S>>, t: S) -> Option<usize> where S: TimeType {
+  let len = cps.len() as i32;
+  if len < 2 {
+    return None;
+  }
+
+  let mut down = 0;
+  let mut up = len - 1;
+
+  while down <= up {
+    let m = (up + down) / 2;
+    if m < 0 || m >= len - 1 {
+      return None;
+    }
+
+    let cp0 = &cps[m as usize];
+
+    if cp0.t > t {
+      up = m-1;
+    } else {
+      let cp1 = &cps[(m+1) as usize];
+
+      if t >= cp1.t {
+        down = m+1;
+      } else {
+        return Some(m as usize)
+      }
+    }
+  }
+
+  None
+}
+
+// Find the lower control point corresponding to a given time. Continuous version. `i` is the last
+// known found index.
+fn around_search_lower_cp<T, S>(cps: &Vec<Key<T, S>>, i: usize, t: S) -> Option<usize> where S: TimeType {
+  let len = cps.len();
+
+  if len < 2 {
+    return None;
+  }
+
+  // the cursor might come from a parameter that had more keys before being edited
+  let mut i = i.min(len - 2);
+
+  loop {
+    let cp = &cps[i];
+    let cp1 = &cps[i+1];
+
+    if t >= cp1.t {
+      if i >= len - 2 {
+        return None;
+      }
+
+      i += 1;
+    } else {
+      if t < cp.t {
+        if i == 0 {
+          return None;
+        }
+
+        i -= 1;
+      } else {
+        break; // found
+      }
+		}
+  }
+
+  Some(i)
+}
+
+// FIXME: not sure we need mutability here, because it would lead into unreproductible effects
+/// Continuous value.
+///
+/// This type wraps a `A` as a function of time – `f32` by default, see `TimeType`. It has a simple
+/// semantic: `at`, giving the value at the wished time.
+pub struct Cont<'a, A, S = Time> {
+  closure: Box<FnMut(S) -> A + 'a>
+}
+
+impl<'a, A, S> Cont<'a, A, S> {
+  /// Like `new`, for any time type.
+  pub fn from_fn<F>(f: F) -> Self where F: 'a + FnMut(S) -> A {
+    Cont {
+      closure: Box::new(f)
+    }
+  }
+
+  pub fn at(&mut self, t: S) -> A {
+    (self.closure)(t)
+  }
+
+  /// Transform the value with a function.
+  pub fn map<B, F>(mut self, mut f: F) -> Cont<'a, B, S> where A: 'a, S: 'a, F: 'a + FnMut(A) -> B {
+    Cont::from_fn(move |t| f(self.at(t)))
+  }
+
+  /// Pair the value with the value of another `Cont`, both sampled at the same time.
+  pub fn zip<B>(mut self, mut other: Cont<'a, B, S>) -> Cont<'a, (A, B), S> where A: 'a, B: 'a, S: 'a + Copy {
+    Cont::from_fn(move |t| (self.at(t), other.at(t)))
+  }
+
+  /// Change the time the value is sampled at.
+  pub fn remap<F>(mut self, f: F) -> Self where A: 'a, S: 'a, F: 'a + Fn(S) -> S {
+    Cont::from_fn(move |t| self.at(f(t)))
+  }
+}
+
+impl<'a, A> Cont<'a, A> {
+  pub fn new<F>(f: F) -> Self where F: 'a + FnMut(f32) -> A {
+    Cont::from_fn(f)
+  }
+
+  /// A value that never changes.
+  pub fn constant(a: A) -> Self where A: 'a + Clone {
+    Cont::new(move |_| a.clone())
+  }
+
+  /// Turn a set of discret values that happen at given moments into a continuous step value. See
+  /// `Step`.
+  pub fn from_discrete(def: A, moments: Vec<(f32, A)>) -> Self where A: 'a + Clone {
+    Step::new(def, moments).into_cont()
+  }
+
+  /// Delay the value by `offset`: what happened at `0` now happens at `offset`.
+  pub fn offset(self, offset: f32) -> Self where A: 'a {
+    self.remap(move |t| t - offset)
+  }
+
+  /// Speed up the value by `scale`. A scale greater than `1` makes it go faster.
+  pub fn scale(self, scale: f32) -> Self where A: 'a {
+    self.remap(move |t| t * scale)
+  }
+
+  /// Repeat the value over `[start; end)` forever, in both directions. If `end` isn’t after
+  /// `start`, the value at `start` is used all the time.
+  pub fn looped(self, start: f32, end: f32) -> Self where A: 'a {
+    self.remap(move |t| {
+      if end <= start {
+        return start;
+      }
+
+      start + modulo(t - start, end - start)
+    })
+  }
+
+  /// Go back and forth over `[start; end]` forever, in both directions. If `end` isn’t after
+  /// `start`, the value at `start` is used all the time.
+  pub fn ping_pong(self, start: f32, end: f32) -> Self where A: 'a {
+    self.remap(move |t| {
+      let len = end - start;
+
+      if len <= 0. {
+        return start;
+      }
+
+      let t = modulo(t - start, 2. * len);
+
+      start + if t > len { 2. * len - t } else { t }
+    })
+  }
+
+  /// Freeze the value outside of `[start; end]`.
+  pub fn clamp(self, start: f32, end: f32) -> Self where A: 'a {
+    self.remap(move |t| t.max(start).min(end))
+  }
+
+  /// Switch to another value at `t`: `self` is used before `t` and `other` from `t` onwards.
+  pub fn switch(mut self, t: f32, mut other: Self) -> Self where A: 'a {
+    let switch_t = t;
+
+    Cont::new(move |t| {
+      if t < switch_t {
+        self.at(t)
+      } else {
+        other.at(t)
+      }
+    })
+  }
+}
+
+// Positive remainder of the division of `a` by `b`.
+fn modulo(a: f32, b: f32) -> f32 {
+  let r = a % b;
+
+  if r < 0. { r + b } else { r }
+}
+
+/// Step track: a value that changes at given moments and stays the same in between.
+///
+/// Unlike `AnimParam`, values don’t need to be interpolable – enums, indices or texture selectors
+/// are fine.
+#[derive(Clone, Debug)]
+pub struct Step<A> {
+  /// Value before the first moment.
+  def: A,
+  /// Moments, sorted by time.
+  moments: Vec<(Time, A)>
+}
+
+impl<A> Step<A> {
+  /// Build a step track out of a default value – used before the first moment – and moments. If
+  /// several moments happen at the same time, the last one wins.
+  pub fn new(def: A, mut moments: Vec<(Time, A)>) -> Self {
+    // stable sort, so that moments at the same time keep their order
+    moments.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    Step {
+      def: def,
+      moments: moments
+    }
+  }
+
+  /// Moments of the track, sorted by time.
+  pub fn moments(&self) -> &[(Time, A)] {
+    &self.moments
+  }
+
+  /// Index of the latest moment at or before `t`, if any.
+  pub fn index_at(&self, t: Time) -> Option<usize> {
+    // number of moments at or before t
+    let mut down = 0;
+    let mut up = self.moments.len();
+
+    while down < up {
+      let m = (down + up) / 2;
+
+      if self.moments[m].0 <= t {
+        down = m + 1;
+      } else {
+        up = m;
+      }
+    }
+
+    if down == 0 { None } else { Some(down - 1) }
+  }
+
+  /// Value of the latest moment at or before `t`, or the default value if there’s none.
+  pub fn get(&self, t: Time) -> &A {
+    self.index_at(t).map_or(&self.def, |i| &self.moments[i].1)
+  }
+
+  /// Owned version of `get`.
+  pub fn at(&self, t: Time) -> A where A: Clone {
+    self.get(t).clone()
+  }
+
+  /// Turn the track into a continuous value.
+  pub fn into_cont<'a>(self) -> Cont<'a, A> where A: 'a + Clone {
+    Cont::new(move |t| self.at(t))
+  }
+}
+
+/// Implement `Interpolate` for a struct which fields all implement `Interpolate`. Each field is
+/// interpolated on its own.
+///
+/// ```ignore
+/// #[derive(Clone, Copy)]
+/// struct Fog {
+///   color: Color,
+///   density: f32
+/// }
+///
+/// impl_interpolate!(Fog { color, density });
+/// ```
+#[macro_export]
+macro_rules! impl_interpolate {
+  ($t:ident { $($field:ident),* }) => {
+    impl $crate::anim::Interpolate for $t {
+      fn lerp(a: Self, b: Self, t: $crate::anim::Time) -> Self {
+        $t {
+          $( $field: $crate::anim::Interpolate::lerp(a.$field, b.$field, t) ),*
+        }
+      }
+
+      fn cubic_hermite(x: (Self, $crate::anim::Time), a: (Self, $crate::anim::Time), b: (Self, $crate::anim::Time), y: (Self, $crate::anim::Time), t: $crate::anim::Time) -> Self {
+        $t {
+          $( $field: $crate::anim::Interpolate::cubic_hermite((x.0.$field, x.1), (a.0.$field, a.1), (b.0.$field, b.1), (y.0.$field, y.1), t) ),*
+        }
+      }
+
+      fn cubic_bezier(a: Self, u: Self, v: Self, b: Self, t: $crate::anim::Time) -> Self {
+        $t {
+          $( $field: $crate::anim::Interpolate::cubic_bezier(a.$field, u.$field, v.$field, b.$field, t) ),*
+        }
+      }
+
+      fn add_delta(base: Self, a: Self, b: Self, w: f32) -> Self {
+        $t {
+          $( $field: $crate::anim::Interpolate::add_delta(base.$field, a.$field, b.$field, w) ),*
+        }
+      }
+    }
+  }
+}
+
+/// Declare a function returning a continuous value built out of keys.
+///
+/// Keys are either given inline, as `(time, value, interpolation)` triplets, or read from a track
+/// of a keyframe file included at compile time – see `animation_struct!`.
+///
+/// ```ignore
+/// simple_animation!(animation_fade, f32, 0., [
+///   (0., 0., Interpolation::Cosine),
+///   (2., 1., Interpolation::Hold)
+/// ], None, Some(Extrapolation::Constant));
+///
+/// simple_animation!(animation_flash, f32, 0., "../data/anim/post.kf", "flash");
+/// ```
+#[macro_export]
+macro_rules! simple_animation {
+  ($name:ident, $t:ty, $def:expr, [ $( ($k:expr, $v:expr, $i:expr) ),* ]) => {
+    simple_animation!($name, $t, $def, [ $( ($k, $v, $i) ),* ], None, None);
+  };
+
+  ($name:ident, $t:ty, $def:expr, $path:expr, $track:expr) => {
+    fn $name<'a>() -> $crate::anim::Cont<'a, $t> {
+      let keyframes = animation_struct!(@parse $path);
+      let mut sampler = $crate::anim::Sampler::new();
+      let keys = animation_struct!(@keys (file keyframes, $path), $t, $track);
+
+      $crate::anim::Cont::new(move |t| {
+        sampler.sample(t, &keys, true).unwrap_or($def)
+      })
+    }
+  };
+
+  ($name:ident, $t:ty, $def:expr, [ $( ($k:expr, $v:expr, $i:expr) ),* ], $pre:expr, $post:expr) => {
+    fn $name<'a>() -> $crate::anim::Cont<'a, $t> {
+      let mut sampler = $crate::anim::Sampler::new();
+      let keys = $crate::anim::AnimParam::new(
+        vec![
+          $( $crate::anim::Key::new($k, $v, $i) ),*
+      ]).with_extrapolation($pre, $post);
+
+      $crate::anim::Cont::new(move |t| {
+        sampler.sample(t, &keys, true).unwrap_or($def)
+      })
+    }
+  }
+}
+
+/// Declare a struct of animated fields and a function returning it as a continuous value, all the
+/// fields being sampled together.
+///
+/// Every field has a type, a default value – used where its keys are undefined – and its keys,
+/// given inline as with `simple_animation!` or as the name of a track of a keyframe file. Keys can
+/// be followed by a `(pre, post)` extrapolation, which overrides the one of the track.
+///
+/// The keyframe file, if any, is included at compile time – its path is relative to the file
+/// invoking the macro – so that release builds don’t depend on it. It’s parsed when the function
+/// is called, which panics if the file is invalid or if a track is missing or has another type.
+///
+/// ```ignore
+/// animation_struct!(PostFx, animation_post_fx, "../data/anim/post.kf", {
+///   curvature: f32 = 0. => "curvature";
+///   jitter: f32 = 0. => [
+///     (40.169, 0.25, Interpolation::Cosine),
+///     (40.669, 0., Interpolation::Hold)
+///   ] (None, Some(Extrapolation::Constant))
+/// });
+///
+/// let mut post_fx = animation_post_fx();
+/// let curvature = post_fx.at(t).curvature;
+/// ```
+#[macro_export]
+macro_rules! animation_struct {
+  ($name:ident, $fn_name:ident, { $( $field:ident : $t:ty = $def:expr => $keys:tt $(($pre:expr, $post:expr))* );* $(;)* }) => {
+    animation_struct!(@define $name, $fn_name, (none), { $( $field : $t = $def => $keys $(($pre, $post))* );* });
+  };
+
+  ($name:ident, $fn_name:ident, $path:expr, { $( $field:ident : $t:ty = $def:expr => $keys:tt $(($pre:expr, $post:expr))* );* $(;)* }) => {
+    animation_struct!(@define $name, $fn_name, (file keyframes, $path), { $( $field : $t = $def => $keys $(($pre, $post))* );* });
+  };
+
+  (@define $name:ident, $fn_name:ident, $source:tt, { $( $field:ident : $t:ty = $def:expr => $keys:tt $(($pre:expr, $post:expr))* );* }) => {
+    #[derive(Clone, Copy)]
+    struct $name {
+      $( $field: $t ),*
+    }
+
+    fn $fn_name<'a>() -> $crate::anim::Cont<'a, $name> {
+      animation_struct!(@load $source);
+
+      // each field gets its own sampler along with its keys
+      $(
+        let mut $field = {
+          let keys = animation_struct!(@keys $source, $t, $keys);
+          $( let keys = keys.with_extrapolation($pre, $post); )*
+          ($crate::anim::Sampler::new(), keys)
+        };
+      )*
+
+      $crate::anim::Cont::new(move |t| {
+        $name {
+          $( $field: $field.0.sample(t, &$field.1, true).unwrap_or($def) ),*
+        }
+      })
+    }
+  };
+
+  (@load (none)) => {};
+
+  (@load (file $keyframes:ident, $path:expr)) => {
+    let $keyframes = animation_struct!(@parse $path);
+  };
+
+  (@parse $path:expr) => {
+    match $crate::anim::keyframe::Keyframes::parse($path, include_str!($path)) {
+      Ok(keyframes) => keyframes,
+      Err(e) => panic!("{}", e)
+    }
+  };
+
+  (@keys $source:tt, $t:ty, [ $( ($k:expr, $v:expr, $i:expr) ),* ]) => {
+    $crate::anim::AnimParam::new(vec![ $( $crate::anim::Key::new($k, $v, $i) ),* ])
+  };
+
+  (@keys (file $keyframes:ident, $path:expr), $t:ty, $track:expr) => {
+    match $keyframes.get::<$t>($track) {
+      Some(keys) => keys.clone(),
+      None => panic!("{}: no {} track named {}", $path, stringify!($t), $track)
+    }
+  };
+}
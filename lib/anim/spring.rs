@@ -0,0 +1,109 @@
+use anim::{Cont, Time, VectorSpace};
+use anim::fixed_step::{DEFAULT_STEP, FixedStep, Simulation};
+
+/// Default simulation step, in seconds.
+pub const DEFAULT_SPRING_STEP: Time = DEFAULT_STEP;
+
+/// Spring/damper follower: a smoothed version of a target value.
+///
+/// The value is pulled towards the target with a force proportional to `stiffness` and slowed
+/// down by `damping`. The spring is simulated with a fixed step, on a time grid starting at a
+/// fixed time, so that the value at a given time only depends on that time: going back in time
+/// resimulates from the closest checkpoint and gives exactly the same values again.
+///
+/// The spring is at rest on its target at the start time; before that, the target is returned
+/// as-is.
+pub struct Spring<'a, T> {
+  model: SpringModel<'a, T>,
+  sim: FixedStep<SpringState<T>>
+}
+
+struct SpringModel<'a, T> {
+  target: Cont<'a, T>,
+  stiffness: f32,
+  damping: f32,
+  start: Time
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SpringState<T> {
+  position: T,
+  velocity: T
+}
+
+impl<'a, T> Spring<'a, T> where T: 'a + VectorSpace {
+  pub fn new(target: Cont<'a, T>, stiffness: f32, damping: f32) -> Self {
+    Spring {
+      model: SpringModel {
+        target: target,
+        stiffness: stiffness,
+        damping: damping,
+        start: 0.
+      },
+      sim: FixedStep::new(0., DEFAULT_SPRING_STEP)
+    }
+  }
+
+  /// A spring that reaches its target as fast as possible without overshooting it.
+  pub fn critically_damped(target: Cont<'a, T>, stiffness: f32) -> Self {
+    Spring::new(target, stiffness, 2. * stiffness.sqrt())
+  }
+
+  /// Set the simulation step.
+  pub fn with_step(self, step: Time) -> Self {
+    Spring {
+      sim: FixedStep::new(self.model.start, step),
+      ..self
+    }
+  }
+
+  /// Set the time the simulation starts at.
+  pub fn with_start(mut self, start: Time) -> Self {
+    self.model.start = start;
+
+    Spring {
+      sim: FixedStep::new(start, self.sim.step()),
+      ..self
+    }
+  }
+
+  /// Smoothed value at `t`.
+  pub fn at(&mut self, t: Time) -> T {
+    if t <= self.model.start {
+      return self.model.target.at(t);
+    }
+
+    self.sim.at(&mut self.model, t).position
+  }
+
+  /// Turn the spring into a continuous value.
+  pub fn into_cont(mut self) -> Cont<'a, T> {
+    Cont::new(move |t| self.at(t))
+  }
+}
+
+impl<'a, T> Simulation for SpringModel<'a, T> where T: 'a + VectorSpace {
+  type State = SpringState<T>;
+
+  // At rest on the target.
+  fn initial_state(&mut self) -> Self::State {
+    let position = self.target.at(self.start);
+
+    SpringState {
+      position: position,
+      velocity: position - position
+    }
+  }
+
+  // Semi-implicit Euler.
+  fn advance(&mut self, state: Self::State, t: Time, dt: Time) -> Self::State {
+    let target = self.target.at(t + dt);
+    let acceleration = (target - state.position) * self.stiffness - state.velocity * self.damping;
+    let velocity = state.velocity + acceleration * dt;
+
+    SpringState {
+      position: state.position + velocity * dt,
+      velocity: velocity
+    }
+  }
+}
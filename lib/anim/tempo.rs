@@ -0,0 +1,263 @@
+use std::fmt;
+
+use anim::{AnimParam, Handle, Interpolation, Key, Time};
+
+/// Number of ticks in a beat.
+pub const TICKS_PER_BEAT: u32 = 480;
+
+/// Time signature, such as 4/4 or 6/8.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimeSignature {
+  /// Number of beats in a bar.
+  pub beats: u32,
+  /// Note value of a beat (4 for a quarter note, 8 for an eighth note, etc.).
+  pub unit: u32
+}
+
+impl TimeSignature {
+  pub fn new(beats: u32, unit: u32) -> Self {
+    TimeSignature {
+      beats: beats,
+      unit: unit
+    }
+  }
+
+  /// Length of a beat, in quarter notes.
+  pub fn beat_length(&self) -> f32 {
+    4. / self.unit as f32
+  }
+}
+
+impl fmt::Display for TimeSignature {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "{}/{}", self.beats, self.unit)
+  }
+}
+
+/// Position in musical time. `bar` and `beat` start at 1, `tick` at 0.
+///
+/// Bars before the first one – i.e. before the offset of the tempo map – are numbered 0, -1, etc.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MusicalTime {
+  pub bar: i32,
+  pub beat: u32,
+  pub tick: u32
+}
+
+impl MusicalTime {
+  pub fn new(bar: i32, beat: u32, tick: u32) -> Self {
+    MusicalTime {
+      bar: bar,
+      beat: beat,
+      tick: tick
+    }
+  }
+
+  /// Parse a `bar:beat[:tick]` position.
+  pub fn parse(s: &str) -> Option<Self> {
+    let parts: Vec<_> = s.split(':').collect();
+
+    if parts.len() < 2 || parts.len() > 3 {
+      return None;
+    }
+
+    let bar = parts[0].parse().ok();
+    let beat = parts[1].parse().ok().and_then(|beat| if beat >= 1 { Some(beat) } else { None });
+    let tick = if parts.len() == 3 {
+      parts[2].parse().ok().and_then(|tick| if tick < TICKS_PER_BEAT { Some(tick) } else { None })
+    } else {
+      Some(0)
+    };
+
+    match (bar, beat, tick) {
+      (Some(bar), Some(beat), Some(tick)) => Some(MusicalTime::new(bar, beat, tick)),
+      _ => None
+    }
+  }
+}
+
+impl fmt::Display for MusicalTime {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    if self.tick == 0 {
+      write!(f, "{}:{}", self.bar, self.beat)
+    } else {
+      write!(f, "{}:{}:{}", self.bar, self.beat, self.tick)
+    }
+  }
+}
+
+/// Map between musical time and seconds.
+///
+/// A tempo map has a time signature, an offset – the time in seconds of the first beat of the
+/// first bar – and a tempo that might change at given positions. Tempi are given in quarter notes
+/// per minute, whatever the time signature: at 120 BPM, a beat lasts 0.5s in 4/4 and 0.25s in
+/// 6/8.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TempoMap {
+  signature: TimeSignature,
+  offset: Time,
+  // sorted by beat; the first one starts at beat 0 and holds the initial tempo
+  segments: Vec<TempoSegment>
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct TempoSegment {
+  // beats since the first beat of the first bar
+  beat: f32,
+  // seconds since the offset
+  time: Time,
+  bpm: f32
+}
+
+impl TempoMap {
+  pub fn new(bpm: f32, signature: TimeSignature) -> Self {
+    TempoMap {
+      signature: signature,
+      offset: 0.,
+      segments: vec![TempoSegment { beat: 0., time: 0., bpm: bpm }]
+    }
+  }
+
+  /// Set the time in seconds of the first beat of the first bar.
+  pub fn with_offset(self, offset: Time) -> Self {
+    TempoMap { offset: offset, ..self }
+  }
+
+  /// Change the tempo at a given position. A change at the very same position than a previous one
+  /// replaces it; a change before the first bar replaces the initial tempo.
+  pub fn with_tempo_change(mut self, at: MusicalTime, bpm: f32) -> Self {
+    let beat = self.beats(at).max(0.);
+
+    match self.segments.iter().position(|segment| segment.beat >= beat) {
+      Some(i) if self.segments[i].beat == beat => self.segments[i].bpm = bpm,
+      Some(i) => self.segments.insert(i, TempoSegment { beat: beat, time: 0., bpm: bpm }),
+      None => self.segments.push(TempoSegment { beat: beat, time: 0., bpm: bpm })
+    }
+
+    // recompute the start times of the segments
+    for i in 1..self.segments.len() {
+      let prev = self.segments[i-1];
+      self.segments[i].time = prev.time + (self.segments[i].beat - prev.beat) * self.beat_duration(prev.bpm);
+    }
+
+    self
+  }
+
+  pub fn signature(&self) -> TimeSignature {
+    self.signature
+  }
+
+  pub fn offset(&self) -> Time {
+    self.offset
+  }
+
+  /// Initial tempo.
+  pub fn bpm(&self) -> f32 {
+    self.segments[0].bpm
+  }
+
+  /// Tempo changes, as positions and tempi.
+  pub fn tempo_changes(&self) -> Vec<(MusicalTime, f32)> {
+    self.segments[1..].iter().map(|segment| (self.musical(segment.beat), segment.bpm)).collect()
+  }
+
+  /// Tempo at `t`, in seconds.
+  pub fn bpm_at(&self, t: Time) -> f32 {
+    self.segment_at_time(t - self.offset).bpm
+  }
+
+  /// Number of beats since the first beat of the first bar at a given position.
+  pub fn beats(&self, m: MusicalTime) -> f32 {
+    (m.bar - 1) as f32 * self.signature.beats as f32 + (m.beat as f32 - 1.) + m.tick as f32 / TICKS_PER_BEAT as f32
+  }
+
+  /// Position after a given number of beats since the first beat of the first bar, rounded to the
+  /// nearest tick.
+  pub fn musical(&self, beats: f32) -> MusicalTime {
+    let ticks = (beats * TICKS_PER_BEAT as f32).round() as i64;
+    let ticks_per_bar = (self.signature.beats * TICKS_PER_BEAT) as i64;
+    let bar = floor_div(ticks, ticks_per_bar);
+    let in_bar = ticks - bar * ticks_per_bar;
+
+    MusicalTime::new(bar as i32 + 1, (in_bar / TICKS_PER_BEAT as i64) as u32 + 1, (in_bar % TICKS_PER_BEAT as i64) as u32)
+  }
+
+  /// Convert a number of beats since the first beat of the first bar into seconds.
+  pub fn beats_to_seconds(&self, beats: f32) -> Time {
+    let segment = self.segment_at_beat(beats);
+    self.offset + segment.time + (beats - segment.beat) * self.beat_duration(segment.bpm)
+  }
+
+  /// Convert seconds into a number of beats since the first beat of the first bar.
+  pub fn seconds_to_beats(&self, t: Time) -> f32 {
+    let t = t - self.offset;
+    let segment = self.segment_at_time(t);
+    segment.beat + (t - segment.time) / self.beat_duration(segment.bpm)
+  }
+
+  /// Convert a position into seconds.
+  pub fn to_seconds(&self, m: MusicalTime) -> Time {
+    self.beats_to_seconds(self.beats(m))
+  }
+
+  /// Convert seconds into a position, rounded to the nearest tick.
+  pub fn to_musical(&self, t: Time) -> MusicalTime {
+    self.musical(self.seconds_to_beats(t))
+  }
+
+  /// Build a key at a given position.
+  pub fn key<T>(&self, at: MusicalTime, value: T, interpolation: Interpolation) -> Key<T> {
+    Key::new(self.to_seconds(at), value, interpolation)
+  }
+
+  /// Snap `t` to the nearest subdivision of a beat – 1 for beats, 2 for half beats, etc.
+  pub fn quantize(&self, t: Time, subdivision: u32) -> Time {
+    let subdivision = subdivision.max(1) as f32;
+    self.beats_to_seconds((self.seconds_to_beats(t) * subdivision).round() / subdivision)
+  }
+
+  /// Snap all the keys of `param` to the nearest subdivision of a beat. Bézier handles are moved
+  /// along with their keys, and the extrapolation is kept.
+  ///
+  /// Keys that end up at the same time are all kept.
+  pub fn quantize_keys<T>(&self, param: &AnimParam<T>, subdivision: u32) -> AnimParam<T> where T: Copy {
+    let keys = param.into_iter().map(|key| {
+      let t = self.quantize(key.t, subdivision);
+      let dt = t - key.t;
+
+      Key {
+        t: t,
+        value: key.value,
+        interpolation: key.interpolation,
+        handles: key.handles.map(|(h0, h1)| (Handle::new(h0.t + dt, h0.value), Handle::new(h1.t + dt, h1.value)))
+      }
+    }).collect();
+
+    AnimParam::new(keys).with_extrapolation(param.pre_extrapolation(), param.post_extrapolation())
+  }
+
+  // Duration of a beat, in seconds, at a given tempo.
+  fn beat_duration(&self, bpm: f32) -> Time {
+    60. / bpm * self.signature.beat_length()
+  }
+
+  fn segment_at_beat(&self, beats: f32) -> TempoSegment {
+    *self.segments.iter().rev().find(|segment| segment.beat <= beats).unwrap_or(&self.segments[0])
+  }
+
+  // `t` is relative to the offset.
+  fn segment_at_time(&self, t: Time) -> TempoSegment {
+    *self.segments.iter().rev().find(|segment| segment.time <= t).unwrap_or(&self.segments[0])
+  }
+}
+
+// Integer division rounding towards negative infinity.
+fn floor_div(a: i64, b: i64) -> i64 {
+  let d = a / b;
+
+  if a % b != 0 && (a < 0) != (b < 0) {
+    d - 1
+  } else {
+    d
+  }
+}
@@ -0,0 +1,81 @@
+use anim::{AnimParam, Sampler, Time};
+use anim::keyframe::{Keyframes, TrackSample, TrackValue};
+
+/// A set of named, typed tracks sampled all at once.
+pub struct Timeline {
+  keyframes: Keyframes,
+  sampler: Sampler
+}
+
+impl Timeline {
+  pub fn new() -> Self {
+    Timeline::from_keyframes(Keyframes::new())
+  }
+
+  /// Build a `Timeline` out of the tracks of a keyframe file.
+  pub fn from_keyframes(keyframes: Keyframes) -> Self {
+    Timeline {
+      keyframes: keyframes,
+      sampler: Sampler::new()
+    }
+  }
+
+  /// Add a track. If a track with the same name already exists, it’s replaced.
+  pub fn insert<T>(&mut self, name: &str, param: AnimParam<T>) where T: TrackValue {
+    self.keyframes.insert(name, T::into_keys(param));
+  }
+
+  /// Tracks of the timeline.
+  pub fn keyframes(&self) -> &Keyframes {
+    &self.keyframes
+  }
+
+  /// Sample all the tracks at `t`. The frame borrows the names of the tracks from the timeline.
+  pub fn sample(&mut self, t: Time) -> Frame {
+    let keyframes = &self.keyframes;
+    let mut values = Vec::with_capacity(keyframes.tracks().len() + keyframes.exprs().len());
+
+    for track in keyframes.tracks() {
+      if let Some(sample) = track.keys.sample(&mut self.sampler, t) {
+        values.push((&track.name[..], sample));
+      }
+    }
+
+    for &(ref name, _) in keyframes.exprs() {
+      if let Some(value) = keyframes.value(name, t) {
+        values.push((&name[..], TrackSample::F32(value)));
+      }
+    }
+
+    Frame {
+      t: t,
+      values: values
+    }
+  }
+
+  /// Time range covered by all the tracks: from the first key of all tracks to the last one.
+  pub fn time_range(&self) -> Option<(Time, Time)> {
+    self.keyframes.tracks().iter().filter_map(|track| track.keys.time_range()).fold(None, |range, (start, end)| {
+      match range {
+        Some((range_start, range_end)) => Some((start.min(range_start), end.max(range_end))),
+        None => Some((start, end))
+      }
+    })
+  }
+}
+
+/// Values of the tracks of a `Timeline` at a given time.
+#[derive(Clone, Debug)]
+pub struct Frame<'a> {
+  /// Time at which the tracks were sampled.
+  pub t: Time,
+  values: Vec<(&'a str, TrackSample)>
+}
+
+impl<'a> Frame<'a> {
+  /// Get the value of a track. Returns `None` if the track doesn’t exist, doesn’t have the expected
+  /// type or isn’t defined at that time.
+  pub fn get<T>(&self, name: &str) -> Option<T> where T: TrackValue {
+    self.values.iter().find(|&&(track_name, _)| track_name == name).and_then(|&(_, ref sample)| T::from_sample(sample))
+  }
+}
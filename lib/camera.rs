@@ -0,0 +1,294 @@
+use nalgebra::{Quaternion, Rotate, UnitQuaternion, cross, norm, normalize};
+use std::fmt::Write as FmtWrite;
+use std::io;
+use std::path::Path;
+
+use anim::{AnimParam, Cont, Interpolation, Key, Time};
+use anim::keyframe::{Keyframes, TrackValue};
+use entity::Entity;
+use transform::{Axis, Orientation, Position, Scale, Transform, X_AXIS, Y_AXIS, Z_AXIS};
+
+/// Default distance a `FlyCamera` travels per move.
+pub const DEFAULT_FLY_SPEED: f32 = 0.1;
+/// Default angle, in radians, a `FlyCamera` turns per cursor unit.
+pub const DEFAULT_FLY_SENSITIVITY: f32 = 0.01;
+/// Captures closer in time than that, in seconds, are considered to happen at the same time.
+pub const CAPTURE_TIME_TOLERANCE: Time = 1e-3;
+
+/// Direction a `FlyCamera` moves towards, relative to where it looks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FlyMove {
+  Left,
+  Right,
+  Forward,
+  Backward,
+  Up,
+  Down
+}
+
+/// Free camera, moved around by hand – typically to find camera keys.
+#[derive(Clone, Copy, Debug)]
+pub struct FlyCamera {
+  pub transform: Transform,
+  /// Distance traveled per move.
+  pub speed: f32,
+  /// Angle, in radians, turned per cursor unit.
+  pub sensitivity: f32
+}
+
+impl FlyCamera {
+  pub fn new(transform: Transform) -> Self {
+    FlyCamera {
+      transform: transform,
+      speed: DEFAULT_FLY_SPEED,
+      sensitivity: DEFAULT_FLY_SENSITIVITY
+    }
+  }
+
+  pub fn with_speed(self, speed: f32) -> Self {
+    FlyCamera { speed: speed, .. self }
+  }
+
+  pub fn with_sensitivity(self, sensitivity: f32) -> Self {
+    FlyCamera { sensitivity: sensitivity, .. self }
+  }
+
+  /// Move the camera once towards `dir`.
+  pub fn fly(&mut self, dir: FlyMove) {
+    // the transform is the one of the view: it moves the world, not the camera
+    let v = match dir {
+      FlyMove::Left => X_AXIS,
+      FlyMove::Right => -X_AXIS,
+      FlyMove::Forward => Z_AXIS,
+      FlyMove::Backward => -Z_AXIS,
+      FlyMove::Up => -Y_AXIS,
+      FlyMove::Down => Y_AXIS
+    };
+
+    let t = self.transform.orientation.inverse_rotate(&(v * self.speed));
+    self.transform = self.transform.translate(t);
+  }
+
+  /// Turn the camera by a relative cursor move: horizontal moves yaw around the world up axis,
+  /// vertical ones pitch around the side axis of the camera, so that looking around never rolls it.
+  pub fn look(&mut self, rel: [f64; 2]) {
+    // the orientation maps world space to view space: rotating after it turns around a view axis,
+    // before it around a world one
+    let yaw = UnitQuaternion::new(Y_AXIS * (rel[0] as f32 * self.sensitivity));
+    let orientation = self.transform.orientation * yaw;
+
+    self.transform = Transform { orientation: orientation, .. self.transform };
+    self.transform = self.transform.orient(X_AXIS, rel[1] as f32 * self.sensitivity);
+  }
+
+  /// Roll the camera by a relative cursor move.
+  pub fn roll(&mut self, rel: f64) {
+    self.transform = self.transform.orient(Z_AXIS, rel as f32 * self.sensitivity);
+  }
+}
+
+/// Camera keys captured along the way, e.g. while flying a `FlyCamera` over the soundtrack.
+///
+/// Every capture records a position key and an orientation key at the given time; capturing again
+/// at the same time – within `CAPTURE_TIME_TOLERANCE` – replaces them. Keys can then be exported as
+/// a keyframe file or as Rust source ready to be pasted in an `AnimParam`.
+#[derive(Clone, Debug)]
+pub struct CameraCapture {
+  interpolation: Interpolation,
+  positions: AnimParam<Position>,
+  orientations: AnimParam<Orientation>
+}
+
+impl CameraCapture {
+  /// Start a capture; captured keys get `interpolation`.
+  ///
+  /// # Panics
+  ///
+  /// Captures don’t have tangent handles, so `interpolation` cannot be `Interpolation::Bezier`;
+  /// use `Interpolation::CatmullRom` for smooth curves.
+  pub fn new(interpolation: Interpolation) -> Self {
+    assert!(interpolation != Interpolation::Bezier, "camera captures cannot use Bézier interpolation");
+
+    CameraCapture {
+      interpolation: interpolation,
+      positions: AnimParam::new(Vec::new()),
+      orientations: AnimParam::new(Vec::new())
+    }
+  }
+
+  /// Record `transform` at `t`. Return the index of the captured keys.
+  pub fn capture(&mut self, t: Time, transform: &Transform) -> usize {
+    if let Some(i) = self.positions.keys().iter().position(|key| (key.t - t).abs() <= CAPTURE_TIME_TOLERANCE) {
+      self.positions.remove(i);
+      self.orientations.remove(i);
+    }
+
+    self.orientations.insert(Key::new(t, transform.orientation, self.interpolation));
+    self.positions.insert(Key::new(t, transform.translation, self.interpolation))
+  }
+
+  /// Remove the keys captured at index `i`.
+  pub fn remove(&mut self, i: usize) -> bool {
+    self.orientations.remove(i);
+    self.positions.remove(i).is_some()
+  }
+
+  /// Number of captures.
+  pub fn len(&self) -> usize {
+    self.positions.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.positions.is_empty()
+  }
+
+  pub fn positions(&self) -> &AnimParam<Position> {
+    &self.positions
+  }
+
+  pub fn orientations(&self) -> &AnimParam<Orientation> {
+    &self.orientations
+  }
+
+  /// Captured keys as the `<name>_position` and `<name>_orientation` tracks of a `Keyframes`.
+  pub fn to_keyframes(&self, name: &str) -> Keyframes {
+    let mut keyframes = Keyframes::new();
+
+    keyframes.insert(&format!("{}_position", name), TrackValue::into_keys(self.positions.clone()));
+    keyframes.insert(&format!("{}_orientation", name), TrackValue::into_keys(self.orientations.clone()));
+
+    keyframes
+  }
+
+  /// Write the captured keys into a keyframe file. See `to_keyframes`.
+  pub fn save<P>(&self, path: P, name: &str) -> io::Result<()> where P: AsRef<Path> {
+    self.to_keyframes(name).save(path)
+  }
+
+  /// Captured keys as `Key::new` lines, position keys first.
+  pub fn to_rust(&self) -> String {
+    let mut src = String::new();
+
+    // writing into a String cannot fail
+    let _ = writeln!(src, "// position keys");
+
+    for key in &self.positions {
+      let p = key.value;
+      let _ = writeln!(src, "Key::new({:?}, Position::new({:?}, {:?}, {:?}), Interpolation::{:?}),", key.t, p.x, p.y, p.z, key.interpolation);
+    }
+
+    let _ = writeln!(src, "// orientation keys");
+
+    for key in &self.orientations {
+      let q = key.value.quaternion();
+      let _ = writeln!(src, "Key::new({:?}, Orientation::new_with_quaternion(Quaternion::new({:?}, {:?}, {:?}, {:?})), Interpolation::{:?}),", key.t, q.w, q.i, q.j, q.k, key.interpolation);
+    }
+
+    src
+  }
+}
+
+/// Camera looking from an animated eye position at an animated target.
+///
+/// The orientation is computed at each sample, the same way `gluLookAt` does, so that only
+/// positions need to be authored. An optional roll track turns the camera around its viewing
+/// direction, in radians. The resulting `Transform` is a view transform – its translation is the
+/// opposite of the eye position – ready to be used with a projection in an `Entity`.
+///
+/// ```ignore
+/// let camera = CameraRig::new(eye.into_cont(origin), target.into_cont(origin))
+///   .with_roll(roll.into_cont(0.))
+///   .into_cont(perspective(w as f32 / h as f32, FOVY, ZNEAR, ZFAR));
+/// ```
+pub struct CameraRig<'a> {
+  eye: Cont<'a, Position>,
+  target: Cont<'a, Position>,
+  roll: Option<Cont<'a, f32>>,
+  up: Axis
+}
+
+impl<'a> CameraRig<'a> {
+  pub fn new(eye: Cont<'a, Position>, target: Cont<'a, Position>) -> Self {
+    CameraRig {
+      eye: eye,
+      target: target,
+      roll: None,
+      up: Y_AXIS
+    }
+  }
+
+  /// Roll the camera around its viewing direction.
+  pub fn with_roll(self, roll: Cont<'a, f32>) -> Self {
+    CameraRig { roll: Some(roll), .. self }
+  }
+
+  /// Set the world axis the top of the camera points to; `Y_AXIS` by default.
+  pub fn with_up(self, up: Axis) -> Self {
+    CameraRig { up: up, .. self }
+  }
+
+  /// View transform at `t`.
+  pub fn at(&mut self, t: Time) -> Transform {
+    let eye = self.eye.at(t);
+    let target = self.target.at(t);
+    let mut orientation = look_at(eye, target, self.up);
+
+    if let Some(ref mut roll) = self.roll {
+      orientation = UnitQuaternion::new(Z_AXIS * roll.at(t)) * orientation;
+    }
+
+    Transform::new(-eye, orientation, Scale::default())
+  }
+
+  /// Turn the rig into a continuous camera, `object` being its projection.
+  pub fn into_cont<T>(mut self, object: T) -> Cont<'a, Entity<T>> where T: 'a + Clone {
+    Cont::new(move |t| Entity::new(object.clone(), self.at(t)))
+  }
+}
+
+/// Orientation of a view looking from `eye` at `target`, with its top towards `up`.
+///
+/// If `target` and `eye` are the same, the identity is returned. If the viewing direction is
+/// along `up`, another up axis is picked so that the camera still has an orientation.
+pub fn look_at(eye: Position, target: Position, up: Axis) -> Orientation {
+  let dir = target - eye;
+
+  if norm(&dir) <= 1e-6 {
+    return UnitQuaternion::new(Axis::new(0., 0., 0.));
+  }
+
+  let f = normalize(&dir);
+  let mut side = cross(&f, &up);
+
+  if norm(&side) <= 1e-6 {
+    // looking straight up or down; any other axis will do
+    side = cross(&f, &(if f.z.abs() < 0.9 { Z_AXIS } else { X_AXIS }));
+  }
+
+  let s = normalize(&side);
+  let u = cross(&s, &f);
+
+  // rows of the rotation matrix from world space to view space
+  rotation_to_quaternion([[s.x, s.y, s.z], [u.x, u.y, u.z], [-f.x, -f.y, -f.z]])
+}
+
+// Convert a rotation matrix, given by rows, into a quaternion.
+fn rotation_to_quaternion(m: [[f32; 3]; 3]) -> Orientation {
+  let trace = m[0][0] + m[1][1] + m[2][2];
+
+  let q = if trace > 0. {
+    let s = (trace + 1.).sqrt() * 2.;
+    Quaternion::new(0.25 * s, (m[2][1] - m[1][2]) / s, (m[0][2] - m[2][0]) / s, (m[1][0] - m[0][1]) / s)
+  } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+    let s = (1. + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.;
+    Quaternion::new((m[2][1] - m[1][2]) / s, 0.25 * s, (m[0][1] + m[1][0]) / s, (m[0][2] + m[2][0]) / s)
+  } else if m[1][1] > m[2][2] {
+    let s = (1. + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.;
+    Quaternion::new((m[0][2] - m[2][0]) / s, (m[0][1] + m[1][0]) / s, 0.25 * s, (m[1][2] + m[2][1]) / s)
+  } else {
+    let s = (1. + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.;
+    Quaternion::new((m[1][0] - m[0][1]) / s, (m[0][2] + m[2][0]) / s, (m[1][2] + m[2][1]) / s, 0.25 * s)
+  };
+
+  UnitQuaternion::new_with_quaternion(q)
+}
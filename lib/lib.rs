@@ -7,18 +7,22 @@ extern crate nalgebra;
 extern crate openal;
 extern crate vorbis;
 
+#[macro_use]
+pub mod report;
+#[macro_use]
+pub mod resource;
+#[macro_use]
 pub mod anim;
+pub mod camera;
 pub mod color;
 pub mod device;
 pub mod entity;
+pub mod noise;
 pub mod objects;
 pub mod projection;
 pub mod pulse;
-#[macro_use]
-pub mod report;
-#[macro_use]
-pub mod resource;
 pub mod shader;
+pub mod shake;
 pub mod texture;
 pub mod transform;
 pub mod window;
@@ -0,0 +1,37 @@
+/// Pseudo-random value in [0; 1] for an integer.
+pub fn hash(seed: u32, i: i32) -> f32 {
+  let mut h = (i as u32 ^ seed.wrapping_mul(0x9e3779b9)).wrapping_mul(0x27d4eb2d);
+  h ^= h >> 15;
+  h = h.wrapping_mul(0x85ebca6b);
+  h ^= h >> 13;
+
+  h as f32 / ::std::u32::MAX as f32
+}
+
+/// Smooth value noise, in [0; 1]: random values at integers, smoothly interpolated in between.
+pub fn noise(seed: u32, x: f32) -> f32 {
+  let i = x.floor();
+  let u = x - i;
+  let a = hash(seed, i as i32);
+  let b = hash(seed, (i as i32).wrapping_add(1));
+
+  a + (b - a) * u * u * (3. - 2. * u)
+}
+
+/// Layered value noise, in [0; 1]. Each of the `octaves` layers goes twice as fast as the previous
+/// one, with half its amplitude.
+pub fn fbm(seed: u32, x: f32, octaves: u32) -> f32 {
+  let mut sum = 0.;
+  let mut total = 0.;
+  let mut amplitude = 1.;
+  let mut x = x;
+
+  for octave in 0..octaves {
+    sum += noise(seed.wrapping_add(octave.wrapping_mul(0x9e3779b9)), x) * amplitude;
+    total += amplitude;
+    amplitude *= 0.5;
+    x *= 2.;
+  }
+
+  sum / total
+}
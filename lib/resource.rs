@@ -1,3 +1,4 @@
+use anim::keyframe::{KeyframeFile, reload_modified};
 use shader::ProgramManager;
 use std::collections::BTreeMap;
 use std::default::Default;
@@ -60,13 +61,15 @@ macro_rules! default_unload_impl {
 
 // Outermost resource manager; i.e. gathers all resource managers.
 pub struct ResourceManager {
-  pub program_manager: ProgramManager
+  pub program_manager: ProgramManager,
+  pub keyframe_manager: <KeyframeFile as Resource>::Manager
 }
 
 impl Default for ResourceManager {
   fn default() -> Self {
     ResourceManager {
-      program_manager: Default::default()
+      program_manager: Default::default(),
+      keyframe_manager: Default::default()
     }
   }
 }
@@ -76,4 +79,12 @@ impl ResourceManager {
   pub fn new() -> Self {
     ResourceManager::default()
   }
+
+  /// Reload the resources that changed on disk. Call it once in a while – typically once per frame.
+  pub fn sync(&mut self) {
+    for e in reload_modified(&mut self.keyframe_manager) {
+      err!("cannot reload keyframes");
+      err!("{}", e);
+    }
+  }
 }
@@ -0,0 +1,177 @@
+use nalgebra::{Rotate, UnitQuaternion, Vector3};
+
+use anim::{Cont, Time};
+use anim::fixed_step::{DEFAULT_STEP, FixedStep, Simulation};
+use noise::fbm;
+use transform::{Orientation, Transform, Translation};
+
+/// Default translation amplitude of a `Shake`.
+pub const DEFAULT_SHAKE_AMPLITUDE: f32 = 0.1;
+/// Default rotation amplitude of a `Shake`, in radians.
+pub const DEFAULT_SHAKE_ROTATION: f32 = 0.02;
+/// Default frequency of a `Shake`, in hertz.
+pub const DEFAULT_SHAKE_FREQUENCY: f32 = 8.;
+/// Default number of noise layers of a `Shake`.
+pub const DEFAULT_SHAKE_OCTAVES: u32 = 3;
+
+/// Camera shake: translation and rotation offsets made of layered smooth noise.
+///
+/// The amplitudes, the frequency and the decay are continuous values, so they can be animated.
+/// Without hits, the shake goes on all the time; with hits – typically beats of the soundtrack –
+/// it starts at full strength on each hit and fades out at `decay` per second.
+///
+/// The shake only depends on its seed and on the time it’s sampled at, whatever the order samples
+/// are taken in, so that record mode and seeking reproduce it exactly. The frequency is integrated
+/// over a fixed time grid, starting at `0`, so that animating it doesn’t make the noise jump.
+///
+/// ```ignore
+/// let mut shake = Shake::new(42)
+///   .with_amplitude(Cont::constant(0.05))
+///   .with_decay(Cont::constant(4.))
+///   .with_hits(beats);
+///
+/// camera.transform = shake.apply(t, camera.transform);
+/// ```
+pub struct Shake<'a> {
+  seed: u32,
+  amplitude: Cont<'a, f32>,
+  rotation: Cont<'a, f32>,
+  frequency: Phase<'a>,
+  decay: Cont<'a, f32>,
+  /// Sorted hit times.
+  hits: Vec<Time>,
+  octaves: u32,
+  /// Integration of the frequency into the noise coordinate.
+  phase: FixedStep<f32>
+}
+
+// Noise coordinate: the frequency integrated over time (midpoint rule).
+struct Phase<'a> {
+  frequency: Cont<'a, f32>
+}
+
+impl<'a> Simulation for Phase<'a> {
+  type State = f32;
+
+  fn initial_state(&mut self) -> f32 {
+    0.
+  }
+
+  fn advance(&mut self, phase: f32, t: Time, dt: Time) -> f32 {
+    phase + self.frequency.at(t + dt * 0.5) * dt
+  }
+}
+
+impl<'a> Shake<'a> {
+  pub fn new(seed: u32) -> Self {
+    Shake {
+      seed: seed,
+      amplitude: Cont::constant(DEFAULT_SHAKE_AMPLITUDE),
+      rotation: Cont::constant(DEFAULT_SHAKE_ROTATION),
+      frequency: Phase { frequency: Cont::constant(DEFAULT_SHAKE_FREQUENCY) },
+      decay: Cont::constant(0.),
+      hits: Vec::new(),
+      octaves: DEFAULT_SHAKE_OCTAVES,
+      phase: FixedStep::new(0., DEFAULT_STEP)
+    }
+  }
+
+  /// Set the translation amplitude.
+  pub fn with_amplitude(self, amplitude: Cont<'a, f32>) -> Self {
+    Shake { amplitude: amplitude, .. self }
+  }
+
+  /// Set the rotation amplitude, in radians.
+  pub fn with_rotation(self, rotation: Cont<'a, f32>) -> Self {
+    Shake { rotation: rotation, .. self }
+  }
+
+  /// Set the frequency, in hertz.
+  pub fn with_frequency(self, frequency: Cont<'a, f32>) -> Self {
+    Shake {
+      frequency: Phase { frequency: frequency },
+      phase: FixedStep::new(0., DEFAULT_STEP),
+      .. self
+    }
+  }
+
+  /// Set how fast the shake fades out after a hit, per second.
+  pub fn with_decay(self, decay: Cont<'a, f32>) -> Self {
+    Shake { decay: decay, .. self }
+  }
+
+  /// Set the times the shake starts again at full strength. Before the first hit, nothing shakes.
+  pub fn with_hits(self, mut hits: Vec<Time>) -> Self {
+    hits.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    Shake { hits: hits, .. self }
+  }
+
+  /// Replace the hit times, e.g. when the keys they come from change.
+  pub fn set_hits(&mut self, mut hits: Vec<Time>) {
+    hits.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    self.hits = hits;
+  }
+
+  /// Set the number of noise layers; each layer goes twice as fast as the previous one, with half
+  /// its amplitude.
+  pub fn with_octaves(self, octaves: u32) -> Self {
+    Shake { octaves: octaves.max(1), .. self }
+  }
+
+  /// Strength of the shake at `t`, in [0; 1].
+  pub fn envelope(&mut self, t: Time) -> f32 {
+    if self.hits.is_empty() {
+      return 1.;
+    }
+
+    match self.hits.iter().rev().find(|&&hit| hit <= t) {
+      Some(&hit) => (-self.decay.at(t).max(0.) * (t - hit)).exp(),
+      None => 0.
+    }
+  }
+
+  /// Translation and rotation offsets at `t`.
+  pub fn at(&mut self, t: Time) -> (Translation, Orientation) {
+    let envelope = self.envelope(t);
+
+    if envelope == 0. {
+      return (Vector3::new(0., 0., 0.), UnitQuaternion::new(Vector3::new(0., 0., 0.)));
+    }
+
+    let x = self.phase(t);
+    let amplitude = self.amplitude.at(t) * envelope;
+    let rotation = self.rotation.at(t) * envelope;
+    let channel = |i: u32| fbm(self.seed.wrapping_add(i.wrapping_mul(0x632be5ab)), x, self.octaves) * 2. - 1.;
+
+    let translation = Vector3::new(channel(0), channel(1), channel(2)) * amplitude;
+    let angles = Vector3::new(channel(3), channel(4), channel(5)) * rotation;
+
+    (translation, UnitQuaternion::new(angles))
+  }
+
+  /// Shake a view transform at `t`. Offsets are expressed along the axes of the view, so that
+  /// the camera shakes around itself.
+  pub fn apply(&mut self, t: Time, transform: Transform) -> Transform {
+    let (translation, orientation) = self.at(t);
+
+    Transform {
+      translation: transform.translation + transform.orientation.inverse_rotate(&translation),
+      orientation: orientation * transform.orientation,
+      .. transform
+    }
+  }
+
+  /// Turn the shake into continuous translation and rotation offsets.
+  pub fn into_cont(mut self) -> Cont<'a, (Translation, Orientation)> {
+    Cont::new(move |t| self.at(t))
+  }
+
+  // Noise coordinate at `t`: the frequency integrated from 0 to `t`.
+  fn phase(&mut self, t: Time) -> f32 {
+    if t <= 0. {
+      return t * self.frequency.frequency.at(0.);
+    }
+
+    self.phase.at(&mut self.frequency, t)
+  }
+}
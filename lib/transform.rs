@@ -141,3 +141,7 @@ fn translation_matrix(v: Translation) -> Matrix4<f32> {
 impl Default for Scale {
   fn default() -> Self { Scale::new(1., 1., 1.) }
 }
+
+// position and scale are interpolated linearly, orientation spherically
+impl_interpolate!(Scale { x, y, z });
+impl_interpolate!(Transform { translation, orientation, scale });
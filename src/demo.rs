@@ -1,15 +1,20 @@
-use ion::anim::{AnimParam, Cont, Interpolation, Key, Sampler};
+use ion::anim::{AnimParam, Cont, Extrapolation, Interpolation, Key, Sampler, Step};
+use ion::anim::keyframe::{KeyframeFile, Keyframes};
+use ion::anim::timeline::Timeline;
+use ion::camera::{CameraCapture, FlyCamera, FlyMove};
 use ion::color::Color;
 use ion::device::Device;
 use ion::entity::*;
 use ion::objects::{new_cube, new_plane};
 use ion::projection::perspective;
+use ion::resource::{Managed, Resource, ResourceManager};
+use ion::shake::Shake;
 use ion::texture::{load_rgba_texture, save_rgba_texture};
 use ion::window::{self, Action, Keyboard, Mouse, MouseButton, MouseMove, Scroll};
 use luminance::{self, Dim2, Equation, Factor, Flat, M44, Mode, RGBA32F};
 use luminance::pipeline::SomeShadingCommand;
 use luminance_gl::gl33::{Framebuffer, Pipeline, RenderCommand, ShadingCommand, Slot, Tessellation};
-use nalgebra::{Quaternion, Rotate, one, zero};
+use nalgebra::{Quaternion, Rotate, one};
 use std::f32;
 
 //use gui::ProgressBar;
@@ -33,12 +38,10 @@ const EVOKE_LOGO_PATH: &'static str = "data/evoke.png";
 const FOVY: f32 = f32::consts::FRAC_PI_4;
 const ZNEAR: f32 = 0.1;
 const ZFAR: f32 = 200.;
-//const CAMERA_YAW_SENSITIVITY: f32 = 0.01;
-//const CAMERA_PITCH_SENSITIVITY: f32 = 0.01;
-//const CAMERA_STRAFE_SENSITIVITY: f32 = 0.1;
-//const CAMERA_FORWARD_SENSITIVITY: f32 = 0.1;
-//const CAMERA_UPWARD_SENSITIVITY: f32 = 0.1;
+const CAMERA_CAPTURE_PATH: &'static str = "data/anim/camera.kf";
+const POST_FX_PATH: &'static str = "data/anim/post.kf";
 const LOGO_SCALE: f32 = 1.;
+const RECORD_FPS: f32 = 60.;
 
 pub fn init(w: u32, h: u32, kbd: Keyboard, mouse: Mouse, mouse_mv: MouseMove, _: Scroll) -> Result<Box<FnMut() -> bool>, String> {
   // tus logo
@@ -73,6 +76,10 @@ pub fn init(w: u32, h: u32, kbd: Keyboard, mouse: Mouse, mouse_mv: MouseMove, _:
                       None)
   };
 
+  // logos, along with their quads, and which one is shown when
+  let logos = [(tus_logo, tus_logo_quad), (evoke_logo, evoke_logo_quad)];
+  let logo_step = Step::new(0, vec![(10., 1)]);
+
   let quad_tex_program = new_quad_tex_program().unwrap();
 
   let back_buffer = Framebuffer::default((w, h));
@@ -108,18 +115,40 @@ pub fn init(w: u32, h: u32, kbd: Keyboard, mouse: Mouse, mouse_mv: MouseMove, _:
   let skybox = new_cube();
   let skybox_program = new_skybox_program().unwrap();
 
-  //let mut cursor_at = [0., 0.]; // last cursor position known
-  //let mut cursor_down_at = [0., 0.]; // position where the cursor was pressed
-  //let mut cursor_left_down = false;
-  //let mut cursor_right_down = false;
+  // debug camera; when set, it replaces the animated camera
+  let mut fly_camera: Option<FlyCamera> = None;
+  let mut camera_capture = CameraCapture::new(Interpolation::Linear);
+  let mut cursor_at = [0., 0.]; // last cursor position known
+  let mut cursor_left_down = false;
+  let mut cursor_right_down = false;
+
+  // [dev only] resources reloaded when they change on disk
+  let mut resources = ResourceManager::new();
 
   // animation
-  let mut anim_cam = animation_camera(w, h);
-  let mut anim_color_mask = animation_color_mask();
-  let mut anim_chromatic_aberration = animation_chromatic_aberration();
-  let mut anim_curvature = animation_curvature();
-  let mut anim_logo_mask = animation_logo_mask();
-  let mut anim_jitter = animation_jitter();
+  let mut anim_cam = animation_camera(w, h, camera_position_keys(), camera_orientation_keys());
+  let post_fx_file = if cfg!(debug_assertions) {
+    // tweak the post-processing keys while the demo runs
+    match KeyframeFile::load(&mut resources.keyframe_manager, POST_FX_PATH, false) {
+      Ok(file) => Some(file),
+      Err(e) => {
+        err!("{}", e);
+        None
+      }
+    }
+  } else {
+    None
+  };
+  let post_keyframes = match post_fx_file.as_ref().and_then(|file| file.upgrade()) {
+    Some(file) => file.keyframes().clone(),
+    None => try!(Keyframes::parse(POST_FX_PATH, include_str!("../data/anim/post.kf")).map_err(|e| format!("{}", e)))
+  };
+  let mut post_fx_revision = post_fx_file.as_ref().and_then(|file| file.upgrade()).map(|file| file.revision());
+  let mut anim_post_fx = match post_fx_file {
+    Some(ref file) => animation_post_fx_live(file.clone()),
+    None => animation_post_fx()
+  };
+  let mut camera_shake = camera_shake(try!(jitter_hits(&post_keyframes)));
 
   let mut dev = Device::new(TRACK_PATH);
 
@@ -127,71 +156,84 @@ pub fn init(w: u32, h: u32, kbd: Keyboard, mouse: Mouse, mouse_mv: MouseMove, _:
     dev.toggle(); // play the goddamn demo
   }
 
+  let end = demo_end(&post_keyframes);
   let mut t = 0.;
+  let mut frame: i64 = 0; // frames recorded so far; time is derived from it to avoid drifting
   let mut image_i = 1000;
 
   Ok(Box::new(move || {
     if cfg!(feature = "record") {
-      info!("rendering {}%", 100. * t / 90.);
+      t = frame as f32 / RECORD_FPS;
+      info!("rendering {}%", 100. * t / end);
     } else {
       t = dev.playback_cursor();
     }
 
-    // while let Ok((mouse_button, action)) = mouse.try_recv() {
-    //   match (mouse_button, action) {
-    //     (MouseButton::Button1, Action::Press) => {
-    //       cursor_left_down = true;
-    //       cursor_down_at = cursor_at;
-    //     },
-    //     (MouseButton::Button1, Action::Release) => {
-    //       cursor_left_down = false;
-
-    //       if time_panel.is_cursor_in(cursor_down_at) {
-    //         let c = cursor_at[0] as f32 / w as f32;
-    //         dev.set_cursor(c.min(1.).max(0.));
-    //       }
-    //     },
-    //     (MouseButton::Button2, Action::Press) => {
-    //       cursor_right_down = true;
-    //       cursor_down_at = cursor_at;
-    //     },
-    //     (MouseButton::Button2, Action::Release) => {
-    //       cursor_right_down = false;
-    //     },
-    //     _ => {}
-    //   }
-    // }
-
-    // while let Ok(cursor_now) = mouse_mv.try_recv() {
-    //   if time_panel.is_cursor_in(cursor_down_at) && cursor_left_down {
-    //     let c = cursor_at[0] as f32 / w as f32;
-    //     dev.set_cursor(c.min(1.).max(0.));
-    //   } else {
-    //     handle_camera_cursor(&mut camera, cursor_left_down, cursor_right_down, cursor_now, &mut cursor_at);
-    //   }
-
-    //   cursor_at = cursor_now;
-    // }
+    if cfg!(debug_assertions) {
+      resources.sync();
+
+      // the shake follows the jitter keys
+      if let Some(file) = post_fx_file.as_ref().and_then(|file| file.upgrade()) {
+        if post_fx_revision != Some(file.revision()) {
+          post_fx_revision = Some(file.revision());
+          let hits = jitter_hits(&file.keyframes());
+
+          match hits {
+            Ok(hits) => camera_shake.set_hits(hits),
+            Err(e) => err!("{}", e)
+          }
+        }
+      }
+    }
+
+    while let Ok((mouse_button, action)) = mouse.try_recv() {
+      match mouse_button {
+        MouseButton::Button1 => cursor_left_down = action == Action::Press,
+        MouseButton::Button2 => cursor_right_down = action == Action::Press,
+        _ => {}
+      }
+    }
+
+    while let Ok(cursor_now) = mouse_mv.try_recv() {
+      if let Some(ref mut fly) = fly_camera {
+        handle_camera_cursor(fly, cursor_left_down, cursor_right_down, cursor_now, &cursor_at);
+      }
+
+      cursor_at = cursor_now;
+    }
 
     while let Ok((key, action)) = kbd.try_recv() {
       if action == Action::Release {
-        if key == window::Key::Escape {
-          return false;
+        match key {
+          window::Key::Escape => return false,
+          window::Key::Tab => { // toggle the debug camera, starting where the animated one is
+            fly_camera = match fly_camera {
+              Some(_) => None,
+              None => Some(FlyCamera::new(camera.transform))
+            };
+          },
+          _ => handle_device_keys(&mut dev, key)
         }
+      } else if let Some(ref mut fly) = fly_camera {
+        handle_camera_keys(fly, &mut camera_capture, key, t);
       }
-      // } else {
-      //   handle_camera_keys(&mut camera, key, t);
-      //   handle_device_keys(&mut dev, key);
-      // }
     }
 
-    // TODO: comment that line to enable debug camera
     camera = anim_cam.at(t);
-    let cmask = anim_color_mask.at(t);
-    let caberration = anim_chromatic_aberration.at(t);
-    let acurvature = anim_curvature.at(t);
-    let logo_mask = anim_logo_mask.at(t);
-    let ajitter = anim_jitter.at(t);
+
+    if let Some(ref fly) = fly_camera {
+      camera.transform = fly.transform;
+    } else {
+      camera.transform = camera_shake.apply(t, camera.transform);
+    }
+
+    let PostFx {
+      color_mask: cmask,
+      chromatic_aberration: caberration,
+      curvature: acurvature,
+      logo_mask,
+      jitter: ajitter
+    } = anim_post_fx.at(t);
 
     // update the camera
     lines_program.update(|&(ref proj, ref view, ref jitter, ref curvature)| {
@@ -278,17 +320,17 @@ pub fn init(w: u32, h: u32, kbd: Keyboard, mouse: Mouse, mouse_mv: MouseMove, _:
                                                           None)
                                      ]);
     // render the logo
-    let t2 = t;
+    let (ref logo, ref logo_quad) = logos[*logo_step.get(t)];
     let logo_cmd = ShadingCommand::new(&quad_tex_program,
                                        |&(ref tex, ref mask)| {
-                                         tex.update(if t2 <= 10. { &tus_logo } else { &evoke_logo });
+                                         tex.update(logo);
                                          mask.update(logo_mask);
                                        },
                                        vec![
                                         RenderCommand::new(Some((Equation::Additive, Factor::SrcAlpha, Factor::SrcAlphaComplement)),
                                                            false,
                                                            |_| {},
-                                                           if t <= 10. { &tus_logo_quad} else { &evoke_logo_quad},
+                                                           logo_quad,
                                                            1,
                                                            None)
                                        ]);
@@ -311,80 +353,77 @@ pub fn init(w: u32, h: u32, kbd: Keyboard, mouse: Mouse, mouse_mv: MouseMove, _:
       // dump frames 
       save_rgba_texture(&record_buffer.color_slot.texture, format!("record/{}.png", image_i));
 
-      t += 1. / 60.; // increment time to target 60 FPS
+      frame += 1;
       image_i += 1;
     } else {
       Pipeline::new(&back_buffer, [0., 0., 0., 1.], shading_cmds).run();
     }
 
-    // leave the demo if we pass over 90 seconds of runtime
-    t <= 90.
+    // leave the demo once we pass its end
+    t <= end
   }))
 }
 
-// fn handle_camera_cursor(camera: &mut Entity<M44>, left_down: bool, right_down: bool, cursor_now: [f64; 2], cursor_at: &[f64; 2]) {
-//   let rel = [cursor_now[0] - cursor_at[0], cursor_now[1] - cursor_at[1]];
-// 
-//   if left_down {
-//     camera.transform = camera.orient(Y_AXIS, rel[0] as f32 * CAMERA_YAW_SENSITIVITY);
-//     camera.transform = camera.orient(X_AXIS, rel[1] as f32 * CAMERA_PITCH_SENSITIVITY);
-//   }
-// 
-//   if right_down {
-//     camera.transform = camera.orient(Z_AXIS, rel[0] as f32 * CAMERA_YAW_SENSITIVITY);
-//   }
-// }
-// 
-// fn handle_camera_keys(camera: &mut Entity<M44>, key: window::Key, t: f32) {
-//   match key {
-//     window::Key::A => {
-//       let left = camera.transform.orientation.inverse_rotate(&(X_AXIS * CAMERA_STRAFE_SENSITIVITY));
-//       camera.transform = camera.translate(left);
-//     },
-//     window::Key::D => {
-//       let right = camera.transform.orientation.inverse_rotate(&(X_AXIS * -CAMERA_STRAFE_SENSITIVITY));
-//       camera.transform = camera.translate(right);
-//     },
-//     window::Key::W => {
-//       let forward = camera.transform.orientation.inverse_rotate(&(Z_AXIS * CAMERA_FORWARD_SENSITIVITY));
-//       camera.transform = camera.translate(forward);
-//     },
-//     window::Key::S => {
-//       let backward = camera.transform.orientation.inverse_rotate(&(Z_AXIS * -CAMERA_FORWARD_SENSITIVITY));
-//       camera.transform = camera.translate(backward);
-//     },
-//     window::Key::R => {
-//       let upward = camera.transform.orientation.inverse_rotate(&(Y_AXIS * -CAMERA_UPWARD_SENSITIVITY));
-//       camera.transform = camera.translate(upward);
-//     },
-//     window::Key::F => {
-//       let downward = camera.transform.orientation.inverse_rotate(&(Y_AXIS * CAMERA_UPWARD_SENSITIVITY));
-//       camera.transform = camera.translate(downward);
-//     },
-//     window::Key::C => { // print camera information on stdout (useful for animation keys)
-//       let p = camera.transform.translation;
-//       let q = camera.transform.orientation.quaternion();
-//       info!("position: Key::new({}, Position::new({}, {}, {})),", t, p[0], p[1], p[2]);
-//       info!("orientation: Key::new({}, Orientation::new_with_quaternion(Quaternion::new({}, {}, {}, {}))),", t, q[0], q[1], q[2], q[3]);
-//       info!("");
-//     },
-//     _ => {}
-//   }
-// }
-// 
-// fn handle_device_keys(dev: &mut Device, key: window::Key) {
-//   match key {
-//     window::Key::Space => {
-//       dev.toggle();
-//     },
-//     _ => {}
-//   }
-// }
-
-fn animation_camera<'a>(w: u32, h: u32) -> Cont<'a, Entity<M44>> {
-  // position keys
+fn handle_camera_cursor(camera: &mut FlyCamera, left_down: bool, right_down: bool, cursor_now: [f64; 2], cursor_at: &[f64; 2]) {
+  let rel = [cursor_now[0] - cursor_at[0], cursor_now[1] - cursor_at[1]];
+
+  if left_down {
+    camera.look(rel);
+  }
+
+  if right_down {
+    camera.roll(rel[0]);
+  }
+}
+
+fn handle_camera_keys(camera: &mut FlyCamera, capture: &mut CameraCapture, key: window::Key, t: f32) {
+  match key {
+    window::Key::A => camera.fly(FlyMove::Left),
+    window::Key::D => camera.fly(FlyMove::Right),
+    window::Key::W => camera.fly(FlyMove::Forward),
+    window::Key::S => camera.fly(FlyMove::Backward),
+    window::Key::R => camera.fly(FlyMove::Up),
+    window::Key::F => camera.fly(FlyMove::Down),
+    window::Key::C => { // capture the camera at the current time
+      let i = capture.capture(t, &camera.transform);
+      info!("captured camera key {} at {}", i, t);
+    },
+    window::Key::E => { // export the captured keys, both as a keyframe file and as Rust source
+      match capture.save(CAMERA_CAPTURE_PATH, "camera") {
+        Ok(_) => info!("{} camera keys saved in {}", capture.len(), CAMERA_CAPTURE_PATH),
+        Err(e) => err!("cannot save camera keys: {}", e)
+      }
+
+      info!("\n{}", capture.to_rust());
+    },
+    _ => {}
+  }
+}
+
+fn handle_device_keys(dev: &mut Device, key: window::Key) {
+  match key {
+    window::Key::Space => {
+      dev.toggle();
+    },
+    _ => {}
+  }
+}
+
+fn animation_camera<'a>(w: u32, h: u32, pos_keys: AnimParam<Position>, orient_keys: AnimParam<Orientation>) -> Cont<'a, Entity<M44>> {
   let mut pos_sampler = Sampler::new();
-  let pos_keys = AnimParam::new(
+  let mut orient_sampler = Sampler::new();
+
+  Cont::new(move |t| {
+    let pos = pos_sampler.sample(t, &pos_keys, true).unwrap_or(Position::new(0., 0., 0.)); // FIXME: release
+    let orient = orient_sampler.sample(t, &orient_keys, true).unwrap_or(Orientation::new(X_AXIS)); // FIXME: release
+    let scale = Scale::default();
+
+    Entity::new(perspective(w as f32 / h as f32, FOVY, ZNEAR, ZFAR), Transform::new(pos, orient, scale))
+  })
+}
+
+fn camera_position_keys() -> AnimParam<Position> {
+  AnimParam::new(
     vec![
       Key::new(0., Position::new(0., 0., 0.), Interpolation::Hold),
       Key::new(4.69, Position::new(-5.978943, -0.08311983, -2.977364), Interpolation::Linear),
@@ -419,13 +458,11 @@ fn animation_camera<'a>(w: u32, h: u32) -> Cont<'a, Entity<M44>> {
       Key::new(77.21, Position::new(-14.817427, -0.2119409, -2.3797803), Interpolation::Hold),
       Key::new(79.857, Position::new(-10.017522, -0.18722507, -15.92794), Interpolation::Linear),
       Key::new(82.458, Position::new(-9.439825, -0.32472718, 2.9556136), Interpolation::Hold),
+  ]).with_extrapolation(None, Some(Extrapolation::Constant))
+}
 
-      Key::new(1000., Position::new(0., 0., 0.), Interpolation::Hold),
-  ]);
-
-  // orientation keys
-  let mut orient_sampler = Sampler::new();
-  let orient_keys = AnimParam::new(
+fn camera_orientation_keys() -> AnimParam<Orientation> {
+  AnimParam::new(
     vec![
       Key::new(0., Orientation::new_with_quaternion(Quaternion::new(0.7219135, -0.6905788, -0.040629696, 0.017061736)), Interpolation::Hold),
       Key::new(4.69, Orientation::new_with_quaternion(Quaternion::new(0.67423373, 0.2073435, 0.7026737, 0.09303007)), Interpolation::Linear),
@@ -460,361 +497,66 @@ fn animation_camera<'a>(w: u32, h: u32) -> Cont<'a, Entity<M44>> {
       Key::new(79.857, Orientation::new_with_quaternion(Quaternion::new(0.9990175, 0.04241703, -0.012645834, 0.0014209902)), Interpolation::Linear),
       Key::new(82.458, Orientation::new_with_quaternion(Quaternion::new(0.9998423, -0.008315526, -0.015410957, 0.0023272862)), Interpolation::Cosine),
       Key::new(88., Orientation::new_with_quaternion(Quaternion::new(0.74239457, -0.66299695, 0.08556984, -0.044163752)), Interpolation::Hold),
+  ]).with_extrapolation(None, Some(Extrapolation::Constant))
+}
 
-      Key::new(1000., Orientation::new_with_quaternion(Quaternion::new(0.7219135, -0.6905788, -0.040629696, 0.017061736)), Interpolation::Hold),
-  ]);
+// camera shake on the jitter hits, fading out quickly
+fn camera_shake<'a>(hits: Vec<f32>) -> Shake<'a> {
+  Shake::new(0xe16)
+    .with_amplitude(Cont::constant(0.05))
+    .with_rotation(Cont::constant(0.01))
+    .with_frequency(Cont::constant(12.))
+    .with_decay(Cont::constant(4.))
+    .with_hits(hits)
+}
 
-  Cont::new(move |t| {
-    let pos = pos_sampler.sample(t, &pos_keys, true).unwrap_or(Position::new(0., 0., 0.)); // FIXME: release
-    let orient = orient_sampler.sample(t, &orient_keys, true).unwrap_or(Orientation::new(X_AXIS)); // FIXME: release
-    let scale = Scale::default();
+// times the jitter bursts start at, i.e. its keys lifting it off zero
+fn jitter_hits(post_keyframes: &Keyframes) -> Result<Vec<f32>, String> {
+  let jitter = try!(post_keyframes.get::<f32>("jitter").ok_or_else(|| format!("{}: jitter is not defined", POST_FX_PATH)));
+  let mut prev = 0.;
 
-    Entity::new(perspective(w as f32 / h as f32, FOVY, ZNEAR, ZFAR), Transform::new(pos, orient, scale))
-  })
+  Ok(jitter.into_iter().filter_map(|key| {
+    let hit = prev == 0. && key.value > 0.;
+    prev = key.value;
+
+    if hit { Some(key.t) } else { None }
+  }).collect())
 }
 
-simple_animation!(animation_color_mask, Color, one(), [
-  (0., zero(), Interpolation::Cosine),
-  (2.35, one(), Interpolation::Cosine),
-  (4.69, zero(), Interpolation::Cosine),
-  (6., one(), Interpolation::Cosine),
-  (9., zero(), Interpolation::Cosine),
-  (11., one(), Interpolation::Cosine),
-  (12., zero(), Interpolation::Cosine),
-  (13., one(), Interpolation::Cosine),
-  (15.126, zero(), Interpolation::Cosine),
-  (17.5, one(), Interpolation::Cosine),
-  (19.5, zero(), Interpolation::Cosine),
-  (21.76, one(), Interpolation::Cosine),
-  (23.25, zero(), Interpolation::Cosine),
-  (25., one(), Interpolation::Cosine),
-  (26.58, zero(), Interpolation::Cosine),
-  (29., one(), Interpolation::Cosine),
-  (30.5, zero(), Interpolation::Cosine),
-  (32.5, one(), Interpolation::Hold),
-  (48.6, Color::new(0.667, 0.408, 0.224), Interpolation::Cosine),
-  (48.8, Color::new(0.659, 0.22, 0.231), Interpolation::Cosine),
-  (49., Color::new(0.392, 0.137, 0.404), Interpolation::Cosine),
-  (49.2, Color::new(0.404, 0.137, 0.392), Interpolation::Cosine),
-  (49.4, Color::new(0.212, 0.302, 0.2), Interpolation::Cosine),
-  (49.5, one(), Interpolation::Hold),
-  (50.86, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (51.16, one(), Interpolation::Hold),
-  (56., Color::new(10., 10., 10.), Interpolation::Cosine),
-  (56.3, one(), Interpolation::Hold),
-  (59.24, Color::new(0.667, 0.408, 0.224), Interpolation::Cosine),
-  (59.44, Color::new(0.659, 0.22, 0.231), Interpolation::Cosine),
-  (59.64, Color::new(0.392, 0.137, 0.404), Interpolation::Cosine),
-  (59.84, Color::new(0.404, 0.137, 0.392), Interpolation::Cosine),
-  (60.04, Color::new(0.212, 0.302, 0.2), Interpolation::Cosine),
-  (60.14, one(), Interpolation::Hold),
-  (61.41, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (61.71, one(), Interpolation::Hold),
-  (66.64, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (66.94, one(), Interpolation::Hold),
-  (69.3, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (69.6, one(), Interpolation::Hold),
-  (69.83, Color::new(0.667, 0.408, 0.224), Interpolation::Cosine),
-  (70.03, Color::new(0.659, 0.22, 0.231), Interpolation::Cosine),
-  (70.23, Color::new(0.392, 0.137, 0.404), Interpolation::Cosine),
-  (70.43, Color::new(0.404, 0.137, 0.392), Interpolation::Cosine),
-  (70.63, Color::new(0.212, 0.302, 0.2), Interpolation::Cosine),
-  (70.83, one(), Interpolation::Hold),
-  (71.92, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (72.22, one(), Interpolation::Hold),
-  (74.54, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (74.84, one(), Interpolation::Hold),
-  (77.21, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (77.51, one(), Interpolation::Hold),
-  (79.84, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (80.14, one(), Interpolation::Hold),
-  (81.09, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (81.39, one(), Interpolation::Hold),
-  (81.79, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (82.09, one(), Interpolation::Hold),
-  (82.11, Color::new(10., 10., 10.), Interpolation::Cosine),
-  (82.41, one(), Interpolation::Hold),
-
-  (1000., zero(), Interpolation::Hold)
-]);
-
-simple_animation!(animation_chromatic_aberration, f32, 1., [
-  (48.6, 50., Interpolation::Cosine),
-  (48.8, 20., Interpolation::Cosine),
-  (49.5, 50., Interpolation::Cosine),
-  (49.7, 1., Interpolation::Hold),
-  (59.24, 50., Interpolation::Cosine),
-  (59.44, 20., Interpolation::Cosine),
-  (60.04, 50., Interpolation::Cosine),
-  (60.24, 1., Interpolation::Hold),
-  (69.73, 50., Interpolation::Cosine),
-  (69.83, 20., Interpolation::Cosine),
-  (70.13, 50., Interpolation::Cosine),
-  (70.33, 1., Interpolation::Hold)
-]);
-
-simple_animation!(animation_curvature, f32, 0., [
-  (15.25, 0., Interpolation::Cosine),
-  (19.5, 1., Interpolation::Hold),
-  (19.5, 0., Interpolation::Cosine),
-  (23.25, 1., Interpolation::Hold),
-  (23.25, 0., Interpolation::Cosine),
-  (26.58, 0.5, Interpolation::Hold),
-  (26.58, 1., Interpolation::Hold),
-  (30.5, 0., Interpolation::Hold),
-  (53.42, 1., Interpolation::Hold),
-  (79.857, 0., Interpolation::Hold)
-]);
-
-simple_animation!(animation_logo_mask, f32, 0., [
-  (0., 0., Interpolation::Cosine),
-  (2.35, 1., Interpolation::Cosine),
-  (4.69, 0., Interpolation::Hold),
-  (82.4, 0., Interpolation::Cosine),
-  (88., 1., Interpolation::Hold),
-  (100., 0., Interpolation::Hold)
-]);
-
-simple_animation!(animation_jitter, f32, 0., [
-  (40.169, 0.25, Interpolation::Cosine),
-  (40.669, 0., Interpolation::Hold),
-  (41.489, 0.25, Interpolation::Cosine),
-  (41.989, 0., Interpolation::Hold),
-  (42.788, 0.25, Interpolation::Cosine),
-  (43.288, 0., Interpolation::Hold),
-  (44.434, 1., Interpolation::Cosine),
-  (44.934, 0., Interpolation::Hold),
-  (45.197, 0.5, Interpolation::Cosine),
-  (45.397, 0., Interpolation::Hold),
-  (45.457, 0.5, Interpolation::Cosine),
-  (45.957, 0., Interpolation::Hold),
-  (46.82, 0.25, Interpolation::Cosine),
-  (47.32, 0., Interpolation::Hold),
-  (47.6, 1., Interpolation::Cosine),
-  (47.9, 0., Interpolation::Hold),
-  (47.9, 0.5, Interpolation::Cosine),
-  (48.4, 0., Interpolation::Hold),
-  (48.6, 2., Interpolation::Cosine),
-  (48.8, 1.5, Interpolation::Cosine),
-  (49., 0.25, Interpolation::Cosine),
-  (49.2, 1.6, Interpolation::Cosine),
-  (49.4, 0., Interpolation::Hold),
-  (49.8, 0.5, Interpolation::Cosine),
-  (50.3, 0., Interpolation::Hold),
-  (50.52, 1., Interpolation::Cosine),
-  (50.82, 0., Interpolation::Cosine),
-  (50.88, 1., Interpolation::Linear),
-  (51., 0.25, Interpolation::Linear),
-  (51.15, 1., Interpolation::Linear),
-  (51.3, 0.5, Interpolation::Linear),
-  (51.45, 1., Interpolation::Linear),
-  (51.6, 0.5, Interpolation::Linear),
-  (51.75, 1., Interpolation::Linear),
-  (51.9, 0.5, Interpolation::Linear),
-  (52.05, 1., Interpolation::Linear),
-  (52.2, 0.5, Interpolation::Linear),
-  (52.35, 1., Interpolation::Linear),
-  (52.5, 0.5, Interpolation::Linear),
-  (52.65, 1., Interpolation::Linear),
-  (52.8, 0.5, Interpolation::Linear),
-  (52.95, 1., Interpolation::Linear),
-  (53.1, 0.5, Interpolation::Linear),
-  (53.25, 1., Interpolation::Linear),
-  (53.4, 0.5, Interpolation::Linear),
-  (53.55, 1., Interpolation::Linear),
-  (53.7, 0.5, Interpolation::Linear),
-  (53.85, 1., Interpolation::Linear),
-  (54., 0.5, Interpolation::Linear),
-  (54.15, 1., Interpolation::Linear),
-  (54.3, 0.5, Interpolation::Linear),
-  (54.45, 1., Interpolation::Linear),
-  (54.6, 0.5, Interpolation::Linear),
-  (54.75, 1., Interpolation::Linear),
-  (54.9, 0.5, Interpolation::Linear),
-  (55.05, 1., Interpolation::Linear),
-  (55.2, 0.5, Interpolation::Linear),
-  (55.35, 1., Interpolation::Linear),
-  (55.5, 0.5, Interpolation::Linear),
-  (55.65, 1., Interpolation::Linear),
-  (55.8, 0.5, Interpolation::Linear),
-  (55.95, 1., Interpolation::Linear),
-  (56.1, 0.5, Interpolation::Linear),
-  (56.25, 1., Interpolation::Linear),
-  (56.4, 0.5, Interpolation::Linear),
-  (56.55, 1., Interpolation::Linear),
-  (56.7, 0.5, Interpolation::Linear),
-  (56.85, 1., Interpolation::Linear),
-  (57., 0.5, Interpolation::Linear),
-  (57.15, 1., Interpolation::Linear),
-  (57.3, 0.5, Interpolation::Linear),
-  (57.45, 1., Interpolation::Linear),
-  (57.6, 0.5, Interpolation::Linear),
-  (57.75, 1., Interpolation::Linear),
-  (57.9, 0.5, Interpolation::Linear),
-  (58.05, 1., Interpolation::Linear),
-  (58.2, 0.5, Interpolation::Linear),
-  (58.35, 1., Interpolation::Linear),
-  (58.5, 0.5, Interpolation::Linear),
-  (58.65, 1., Interpolation::Linear),
-  (58.8, 0.5, Interpolation::Linear),
-  (58.95, 1., Interpolation::Linear),
-  (59.1, 0.5, Interpolation::Linear),
-  (59.25, 1., Interpolation::Linear),
-  (59.4, 0.5, Interpolation::Linear),
-  (59.55, 1., Interpolation::Linear),
-  (59.7, 0.5, Interpolation::Linear),
-  (59.85, 1., Interpolation::Linear),
-  (60., 0.5, Interpolation::Linear),
-  (60.15, 1., Interpolation::Linear),
-  (60.3, 0.5, Interpolation::Linear),
-  (60.45, 1., Interpolation::Linear),
-  (60.6, 0.5, Interpolation::Linear),
-  (60.75, 1., Interpolation::Linear),
-  (60.9, 0.5, Interpolation::Linear),
-  (61.05, 1., Interpolation::Linear),
-  (61.2, 0.5, Interpolation::Linear),
-  (61.35, 1., Interpolation::Linear),
-  (61.5, 0.5, Interpolation::Linear),
-  (61.65, 1., Interpolation::Linear),
-  (61.8, 0.5, Interpolation::Linear),
-  (61.95, 1., Interpolation::Linear),
-  (62.1, 0.5, Interpolation::Linear),
-  (62.25, 1., Interpolation::Linear),
-  (62.4, 0.5, Interpolation::Linear),
-  (62.55, 1., Interpolation::Linear),
-  (62.7, 0.5, Interpolation::Linear),
-  (62.85, 1., Interpolation::Linear),
-  (63., 0.5, Interpolation::Linear),
-  (63.15, 1., Interpolation::Linear),
-  (63.3, 0.5, Interpolation::Linear),
-  (63.45, 1., Interpolation::Linear),
-  (63.6, 0.5, Interpolation::Linear),
-  (63.75, 1., Interpolation::Linear),
-  (63.9, 0.5, Interpolation::Linear),
-  (64.05, 1., Interpolation::Linear),
-  (64.2, 0.5, Interpolation::Linear),
-  (64.35, 1., Interpolation::Linear),
-  (64.5, 0.5, Interpolation::Linear),
-  (64.65, 1., Interpolation::Linear),
-  (64.8, 0.5, Interpolation::Linear),
-  (64.95, 1., Interpolation::Linear),
-  (65.1, 0.5, Interpolation::Linear),
-  (65.25, 1., Interpolation::Linear),
-  (65.4, 0.5, Interpolation::Linear),
-  (65.55, 1., Interpolation::Linear),
-  (65.7, 0.5, Interpolation::Linear),
-  (65.85, 1., Interpolation::Linear),
-  (66., 0.5, Interpolation::Linear),
-  (66.15, 1., Interpolation::Linear),
-  (66.3, 0.5, Interpolation::Linear),
-  (66.45, 1., Interpolation::Linear),
-  (66.6, 0.5, Interpolation::Linear),
-  (66.75, 1., Interpolation::Linear),
-  (66.9, 0.5, Interpolation::Linear),
-  (67.05, 1., Interpolation::Linear),
-  (67.2, 0.5, Interpolation::Linear),
-  (67.35, 1., Interpolation::Linear),
-  (67.5, 0.5, Interpolation::Linear),
-  (67.65, 1., Interpolation::Linear),
-  (67.8, 0.5, Interpolation::Linear),
-  (67.95, 1., Interpolation::Linear),
-  (68.1, 0.5, Interpolation::Linear),
-  (68.25, 1., Interpolation::Linear),
-  (68.4, 0.5, Interpolation::Linear),
-  (68.55, 1., Interpolation::Linear),
-  (68.7, 0.5, Interpolation::Linear),
-  (68.85, 1., Interpolation::Linear),
-  (69., 0.5, Interpolation::Linear),
-  (69.15, 1., Interpolation::Linear),
-  (69.3, 0.5, Interpolation::Linear),
-  (69.45, 1., Interpolation::Linear),
-  (69.6, 0.5, Interpolation::Linear),
-  (69.75, 1., Interpolation::Linear),
-  (69.9, 0.5, Interpolation::Linear),
-  (70.05, 1., Interpolation::Linear),
-  (70.2, 0.5, Interpolation::Linear),
-  (70.35, 1., Interpolation::Linear),
-  (70.5, 0.5, Interpolation::Linear),
-  (70.65, 1., Interpolation::Linear),
-  (70.8, 0.5, Interpolation::Linear),
-  (70.95, 1., Interpolation::Linear),
-  (71.1, 0.5, Interpolation::Linear),
-  (71.25, 1., Interpolation::Linear),
-  (71.4, 0.5, Interpolation::Linear),
-  (71.55, 1., Interpolation::Linear),
-  (71.7, 0.5, Interpolation::Linear),
-  (71.85, 1., Interpolation::Linear),
-  (72., 0.5, Interpolation::Linear),
-  (72.15, 1., Interpolation::Linear),
-  (72.3, 0.5, Interpolation::Linear),
-  (72.45, 1., Interpolation::Linear),
-  (72.6, 0.5, Interpolation::Linear),
-  (72.75, 1., Interpolation::Linear),
-  (72.9, 0.5, Interpolation::Linear),
-  (73.05, 1., Interpolation::Linear),
-  (73.2, 0.5, Interpolation::Linear),
-  (73.35, 1., Interpolation::Linear),
-  (73.5, 0.5, Interpolation::Linear),
-  (73.65, 1., Interpolation::Linear),
-  (73.8, 0.5, Interpolation::Linear),
-  (73.95, 1., Interpolation::Linear),
-  (74.1, 0.5, Interpolation::Linear),
-  (74.25, 1., Interpolation::Linear),
-  (74.4, 0.5, Interpolation::Linear),
-  (74.55, 1., Interpolation::Linear),
-  (74.7, 0.5, Interpolation::Linear),
-  (74.85, 1., Interpolation::Linear),
-  (75., 0.5, Interpolation::Linear),
-  (75.15, 1., Interpolation::Linear),
-  (75.3, 0.5, Interpolation::Linear),
-  (75.45, 1., Interpolation::Linear),
-  (75.6, 0.5, Interpolation::Linear),
-  (75.75, 1., Interpolation::Linear),
-  (75.9, 0.5, Interpolation::Linear),
-  (76.05, 1., Interpolation::Linear),
-  (76.2, 0.5, Interpolation::Linear),
-  (76.35, 1., Interpolation::Linear),
-  (76.5, 0.5, Interpolation::Linear),
-  (76.65, 1., Interpolation::Linear),
-  (76.8, 0.5, Interpolation::Linear),
-  (76.95, 1., Interpolation::Linear),
-  (77.1, 0.5, Interpolation::Linear),
-  (77.25, 1., Interpolation::Linear),
-  (77.4, 0.5, Interpolation::Linear),
-  (77.55, 1., Interpolation::Linear),
-  (77.7, 0.5, Interpolation::Linear),
-  (77.85, 1., Interpolation::Linear),
-  (78., 0.5, Interpolation::Linear),
-  (78.15, 1., Interpolation::Linear),
-  (78.3, 0.5, Interpolation::Linear),
-  (78.45, 1., Interpolation::Linear),
-  (78.6, 0.5, Interpolation::Linear),
-  (78.75, 1., Interpolation::Linear),
-  (78.9, 0.5, Interpolation::Linear),
-  (79.05, 1., Interpolation::Linear),
-  (79.2, 0.5, Interpolation::Linear),
-  (79.35, 1., Interpolation::Linear),
-  (79.5, 0.5, Interpolation::Linear),
-  (79.65, 1., Interpolation::Linear),
-  (79.8, 0.5, Interpolation::Linear),
-  (79.95, 1., Interpolation::Linear),
-  (80.1, 0.5, Interpolation::Linear),
-  (80.25, 1., Interpolation::Linear),
-  (80.4, 0.5, Interpolation::Linear),
-  (80.55, 1., Interpolation::Linear),
-  (80.7, 0.5, Interpolation::Linear),
-  (80.85, 1., Interpolation::Linear),
-  (81., 0.5, Interpolation::Linear),
-  (81.15, 1., Interpolation::Linear),
-  (81.3, 0.5, Interpolation::Linear),
-  (81.45, 1., Interpolation::Linear),
-  (81.6, 0.5, Interpolation::Linear),
-  (81.75, 1., Interpolation::Linear),
-  (81.9, 0.5, Interpolation::Linear),
-  (82.05, 1., Interpolation::Linear),
-  (82.2, 0.5, Interpolation::Linear),
-  (82.35, 1., Interpolation::Linear),
-  (88., 0., Interpolation::Hold),
-
-  (1000., 1., Interpolation::Hold)
-]);
+// time the demo ends at: the last key of all its tracks
+fn demo_end(post_keyframes: &Keyframes) -> f32 {
+  let mut timeline = Timeline::from_keyframes(post_keyframes.clone());
+  timeline.insert("camera_position", camera_position_keys());
+  timeline.insert("camera_orientation", camera_orientation_keys());
+
+  timeline.time_range().map_or(0., |(_, end)| end)
+}
+
+// post-processing parameters; their keys live in data/anim/post.kf
+animation_struct!(PostFx, animation_post_fx, "../data/anim/post.kf", {
+  color_mask: Color = one() => "color_mask";
+  chromatic_aberration: f32 = 1. => "chromatic_aberration";
+  curvature: f32 = 0. => "curvature";
+  logo_mask: f32 = 0. => "logo_mask";
+  jitter: f32 = 0. => "jitter"
+});
+
+// [dev only] same as animation_post_fx, but sampling the managed keyframe file, so that its keys
+// can be edited while the demo runs
+fn animation_post_fx_live<'a>(file: Managed<KeyframeFile>) -> Cont<'a, PostFx> {
+  let mut color_mask = KeyframeFile::cont(file.clone(), "color_mask", one());
+  let mut chromatic_aberration = KeyframeFile::cont(file.clone(), "chromatic_aberration", 1.);
+  let mut curvature = KeyframeFile::cont(file.clone(), "curvature", 0.);
+  let mut logo_mask = KeyframeFile::cont(file.clone(), "logo_mask", 0.);
+  let mut jitter = KeyframeFile::cont(file, "jitter", 0.);
+
+  Cont::new(move |t| {
+    PostFx {
+      color_mask: color_mask.at(t),
+      chromatic_aberration: chromatic_aberration.at(t),
+      curvature: curvature.at(t),
+      logo_mask: logo_mask.at(t),
+      jitter: jitter.at(t)
+    }
+  })
+}
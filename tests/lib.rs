@@ -1,8 +1,27 @@
+#[macro_use]
 extern crate ion;
+extern crate nalgebra;
 extern crate rand;
 
 use ion::anim::*;
+use ion::anim::arc_length::*;
+use ion::anim::bake::*;
+use ion::anim::blend::*;
+use ion::anim::expr::*;
+use ion::anim::fit::*;
+use ion::anim::keyframe::*;
+use ion::anim::spring::*;
+use ion::anim::tempo::*;
+use ion::anim::timeline::*;
+use ion::camera::*;
+use ion::resource::{ManagerMap, Resource};
+use ion::shake::*;
+use ion::transform::{Scale, Transform, X_AXIS};
+use nalgebra::{Quaternion, Rotate, UnitQuaternion, Vector3};
 use rand::{Rng, thread_rng};
+use std::env;
+use std::fs::File;
+use std::io::Write;
 
 #[test]
 fn sampler_hold() {
@@ -36,6 +55,115 @@ fn sampler_linear() {
   assert_eq!(sampler.sample(5., &p, true), Some(15.));
 }
 
+#[test]
+fn sampler_catmull_rom() {
+  let mut sampler = Sampler::new();
+  let p = AnimParam::new(vec![
+    Key::new(0., 0., Interpolation::CatmullRom),
+    Key::new(1., 1., Interpolation::CatmullRom),
+    Key::new(2., 2., Interpolation::CatmullRom),
+    Key::new(3., 3., Interpolation::CatmullRom)
+  ]);
+
+  // every segment is sampleable, including the first and the last ones
+  assert_eq!(sampler.sample(0., &p, true), Some(0.));
+  assert_eq!(sampler.sample(0.5, &p, true), Some(0.5));
+  assert_eq!(sampler.sample(1.5, &p, true), Some(1.5));
+  assert_eq!(sampler.sample(2.5, &p, true), Some(2.5));
+  assert_eq!(sampler.sample(2.5, &p, false), Some(2.5));
+  assert_eq!(sampler.sample(3., &p, true), None);
+
+  let p = AnimParam::new(vec![
+    Key::new(0., 10., Interpolation::CatmullRom),
+    Key::new(10., 20., Interpolation::CatmullRom)
+  ]);
+
+  assert_eq!(sampler.sample(0., &p, true), Some(10.));
+  assert_eq!(sampler.sample(5., &p, true), Some(15.));
+  assert_eq!(sampler.sample(10., &p, true), None);
+}
+
+#[test]
+fn sampler_bezier() {
+  let mut sampler = Sampler::new();
+  let p = AnimParam::new(vec![
+    Key::new_bezier(0., 0., Handle::new(0., 0.), Handle::new(0.5, 0.)),
+    Key::new_bezier(1., 1., Handle::new(0.5, 1.), Handle::new(1., 1.))
+  ]);
+
+  assert_eq!(sampler.sample(0., &p, true), Some(0.));
+  assert!((sampler.sample(0.5, &p, true).unwrap() - 0.5).abs() < 1e-5);
+  assert!(sampler.sample(0.1, &p, true).unwrap() < 0.1); // ease in
+  assert!(sampler.sample(0.9, &p, true).unwrap() > 0.9); // ease out
+  assert_eq!(sampler.sample(1., &p, true), None);
+
+  // without handles, a Bézier segment is a straight line
+  let p = AnimParam::new(vec![
+    Key::new(0., 0., Interpolation::Bezier),
+    Key::new(10., 20., Interpolation::Bezier)
+  ]);
+
+  assert!((sampler.sample(2.5, &p, true).unwrap() - 5.).abs() < 1e-4);
+}
+
+fn y_rotation(phi: f32) -> UnitQuaternion<f32> {
+  UnitQuaternion::new(Vector3::new(0., phi, 0.))
+}
+
+// Angle between two orientations, regardless of the hemisphere they lie in.
+fn angle_between(a: UnitQuaternion<f32>, b: UnitQuaternion<f32>) -> f32 {
+  let (a, b) = (a.quaternion(), b.quaternion());
+  let d = (a.w * b.w + a.i * b.i + a.j * b.j + a.k * b.k).abs().min(1.);
+
+  2. * d.acos()
+}
+
+#[test]
+fn sampler_squad() {
+  let mut sampler = Sampler::new();
+  let q = y_rotation(1.).quaternion().clone();
+  let p = AnimParam::new(vec![
+    Key::new(0., y_rotation(0.), Interpolation::CatmullRom),
+    Key::new(1., y_rotation(0.5), Interpolation::CatmullRom),
+    // same orientation as y_rotation(1.), but in the opposite hemisphere
+    Key::new(2., UnitQuaternion::new_with_quaternion(Quaternion::new(-q.w, -q.i, -q.j, -q.k)), Interpolation::CatmullRom),
+    Key::new(3., y_rotation(1.5), Interpolation::CatmullRom)
+  ]);
+
+  // keys lying on the same axis at a constant pace must be interpolated at constant pace too
+  for &(t, phi) in &[(0., 0.), (0.5, 0.25), (1.5, 0.75), (2.25, 1.125), (2.5, 1.25)] {
+    let q = sampler.sample(t, &p, true).unwrap();
+    assert!(angle_between(q, y_rotation(phi)) < 1e-3, "t: {}", t);
+  }
+
+  // the shortest path is taken: rotating X at the middle of the segment never goes past the keys
+  let x = sampler.sample(1.5, &p, true).unwrap().rotate(&Vector3::new(1., 0., 0.));
+  assert!((x.x - f32::cos(0.75)).abs() < 1e-3);
+}
+
+#[test]
+fn sampler_squad_smooth() {
+  let mut sampler = Sampler::new();
+  let p = AnimParam::new(vec![
+    Key::new(0., UnitQuaternion::new(Vector3::new(0., 0., 0.)), Interpolation::CatmullRom),
+    Key::new(1., UnitQuaternion::new(Vector3::new(0.8, 0.2, 0.)), Interpolation::CatmullRom),
+    Key::new(2., UnitQuaternion::new(Vector3::new(0.3, 1.2, 0.4)), Interpolation::CatmullRom),
+    Key::new(3., UnitQuaternion::new(Vector3::new(-0.5, 0.9, 1.)), Interpolation::CatmullRom)
+  ]);
+  let h = 1e-2;
+
+  // the angular speed is continuous when crossing keys
+  for &t in &[1., 2.] {
+    let before = sampler.sample(t - h, &p, true).unwrap();
+    let at = sampler.sample(t, &p, true).unwrap();
+    let after = sampler.sample(t + h, &p, true).unwrap();
+    let speed_before = angle_between(before, at) / h;
+    let speed_after = angle_between(at, after) / h;
+
+    assert!((speed_before - speed_after).abs() < 0.1, "t: {}, {} != {}", t, speed_before, speed_after);
+  }
+}
+
 #[test]
 fn keys_sorted() {
   let nb = 10000;
@@ -58,3 +186,1184 @@ fn keys_sorted() {
     t = key.t;
   }
 }
+
+const KEYFRAMES: &'static str = "\
+# a comment
+track fade f32
+  0 cosine 0
+  2.35 cosine 1 # another comment
+  4.69 hold 0
+
+track position vec3
+  0 bezier 0 0 0 in 0 0 0 0 out 1 0 0 0
+  4.69 linear -5.978943 -0.08311983 -2.977364 in 4 -5 0 -2 out 4.69 -5.978943 -0.08311983 -2.977364
+
+track mask color
+  1.5 hold 1 0.5 0.25
+
+track orientation orientation
+  0 catmull-rom 1 0 0 0
+";
+
+#[test]
+fn keyframes_parse() {
+  let keyframes = Keyframes::parse("test.kf", KEYFRAMES).unwrap();
+  let mut sampler = Sampler::new();
+
+  assert_eq!(keyframes.tracks().len(), 4);
+  assert_eq!(sampler.sample(2.35, keyframes.get::<f32>("fade").unwrap(), true), Some(1.));
+  assert!(keyframes.get::<nalgebra::Vector2<f32>>("fade").is_none());
+  assert!(keyframes.get::<f32>("nope").is_none());
+
+  let position = keyframes.get::<Vector3<f32>>("position").unwrap();
+  let keys: Vec<_> = position.into_iter().collect();
+  assert_eq!(keys[0].handles.unwrap().1, Handle::new(1., Vector3::new(0., 0., 0.)));
+  assert_eq!(keys[1].value, Vector3::new(-5.978943, -0.08311983, -2.977364));
+  assert_eq!(keys[1].interpolation, Interpolation::Linear);
+
+  // colors are vec3 too
+  assert!(keyframes.get::<Vector3<f32>>("mask").is_some());
+  assert_eq!(keyframes.keys("mask").unwrap().type_name(), "color");
+  assert!(keyframes.get::<UnitQuaternion<f32>>("orientation").is_some());
+}
+
+#[test]
+fn keyframes_round_trip() {
+  let keyframes = Keyframes::parse("test.kf", KEYFRAMES).unwrap();
+  let written = keyframes.to_string();
+  let reparsed = Keyframes::parse("written.kf", &written).unwrap();
+
+  assert_eq!(reparsed.to_string(), written);
+
+  let a: Vec<_> = keyframes.get::<f32>("fade").unwrap().into_iter().cloned().collect();
+  let b: Vec<_> = reparsed.get::<f32>("fade").unwrap().into_iter().cloned().collect();
+  assert_eq!(a, b);
+
+  // handles survive on any key, not only on Bézier ones
+  let a: Vec<_> = keyframes.get::<Vector3<f32>>("position").unwrap().into_iter().cloned().collect();
+  let b: Vec<_> = reparsed.get::<Vector3<f32>>("position").unwrap().into_iter().cloned().collect();
+  assert_eq!(a[1].handles.unwrap().0, Handle::new(4., Vector3::new(-5., 0., -2.)));
+  assert_eq!(a, b);
+
+  let src = "track easing f32\n  0 ease-out-back 0\n  1 ease-in-out-bounce 1\n";
+  let keyframes = Keyframes::parse("test.kf", src).unwrap();
+  let keys: Vec<_> = keyframes.get::<f32>("easing").unwrap().into_iter().map(|key| key.interpolation).collect();
+  assert_eq!(keys, vec![Interpolation::EaseOutBack, Interpolation::EaseInOutBounce]);
+  assert_eq!(keyframes.to_string(), src);
+}
+
+#[test]
+fn keyframes_parse_errors() {
+  let error = |src: &str| Keyframes::parse("test.kf", src).unwrap_err();
+
+  let e = error("track fade f32\n  0 cosine 0\n  1 cosin 1");
+  assert_eq!((e.file.as_str(), e.line, e.column), ("test.kf", 3, 5));
+  assert_eq!(e.to_string(), "test.kf:3:5: unknown interpolation cosin");
+
+  let e = error("0 hold 1");
+  assert_eq!((e.line, e.column), (1, 1));
+
+  let e = error("track fade f33");
+  assert_eq!((e.line, e.column), (1, 12));
+
+  let e = error("track p vec3\n0 hold 1 2");
+  assert_eq!((e.line, e.column), (2, 11));
+
+  let e = error("track p vec3\n0 hold 1 x 3");
+  assert_eq!((e.line, e.column), (2, 10));
+
+  let e = error("track p f32\n0 hold 1 2");
+  assert_eq!((e.line, e.column), (2, 10));
+
+  let e = error("track p f32\n0 bezier 1 in 0 1 oops 1 1");
+  assert_eq!((e.line, e.column), (2, 19));
+
+  let e = error("track p f32\ntrack p vec2");
+  assert_eq!((e.line, e.column), (2, 7));
+}
+
+#[test]
+fn keyframe_file_reload() {
+  let path = env::temp_dir().join("ion_keyframe_file_reload.kf");
+  let path = path.to_str().unwrap();
+  let mut manager = ManagerMap::new();
+
+  File::create(path).unwrap().write_all(b"track fade f32\n0 linear 0\n10 hold 10").unwrap();
+
+  let file = KeyframeFile::load(&mut manager, path, false).unwrap();
+  let mut fade = KeyframeFile::cont(file.clone(), "fade", -1.);
+  assert_eq!(fade.at(5.), 5.);
+  assert_eq!(fade.at(20.), -1.);
+
+  // the curve is swapped in place
+  File::create(path).unwrap().write_all(b"track fade f32\n0 linear 0\n10 hold 20").unwrap();
+  KeyframeFile::reload(&mut manager, path).unwrap();
+  assert_eq!(fade.at(5.), 10.);
+  assert!(file.upgrade().is_some());
+
+  // a broken file leaves the curve untouched
+  File::create(path).unwrap().write_all(b"track fade f32\n0 linear").unwrap();
+  assert!(KeyframeFile::reload(&mut manager, path).is_err());
+  assert_eq!(fade.at(5.), 10.);
+
+  KeyframeFile::unload(&mut manager, path);
+  assert_eq!(fade.at(5.), -1.);
+}
+
+#[test]
+fn timeline() {
+  let mut timeline = Timeline::from_keyframes(Keyframes::parse("test.kf", KEYFRAMES).unwrap());
+  timeline.insert("jitter", AnimParam::new(vec![
+    Key::new(-1., 0., Interpolation::Linear),
+    Key::new(3., 4., Interpolation::Hold)
+  ]));
+
+  assert_eq!(timeline.time_range(), Some((-1., 4.69)));
+
+  let frame = timeline.sample(2.35);
+  assert_eq!(frame.get::<f32>("fade"), Some(1.));
+  assert_eq!(frame.get::<f32>("jitter"), Some(3.35));
+  assert!(frame.get::<Vector3<f32>>("position").is_some());
+  assert!(frame.get::<Vector3<f32>>("fade").is_none());
+  assert!(frame.get::<UnitQuaternion<f32>>("orientation").is_none()); // single key
+
+  let frame = timeline.sample(4.);
+  assert!(frame.get::<f32>("jitter").is_none());
+
+  assert_eq!(Timeline::new().time_range(), None);
+}
+
+#[test]
+fn cont_combinators() {
+  let line = || Cont::new(|t| t);
+
+  assert_eq!(Cont::constant(3).at(42.), 3);
+  assert_eq!(line().map(|x| x * 2.).at(3.), 6.);
+  assert_eq!(line().zip(line().map(|x| -x)).at(3.), (3., -3.));
+  assert_eq!(line().offset(10.).at(12.), 2.);
+  assert_eq!(line().scale(2.).at(3.), 6.);
+  assert_eq!(line().offset(10.).scale(2.).at(6.), 2.);
+
+  let mut looped = line().looped(1., 3.);
+  assert_eq!(looped.at(1.5), 1.5);
+  assert_eq!(looped.at(3.5), 1.5);
+  assert_eq!(looped.at(0.5), 2.5);
+
+  let mut ping_pong = line().ping_pong(1., 3.);
+  assert_eq!(ping_pong.at(2.), 2.);
+  assert_eq!(ping_pong.at(3.5), 2.5);
+  assert_eq!(ping_pong.at(5.5), 1.5);
+  assert_eq!(ping_pong.at(0.5), 1.5);
+
+  // empty ranges stick to their start
+  assert_eq!(line().looped(2., 2.).at(7.), 2.);
+  assert_eq!(line().ping_pong(2., 2.).at(7.), 2.);
+
+  let mut clamped = line().clamp(1., 3.);
+  assert_eq!(clamped.at(0.), 1.);
+  assert_eq!(clamped.at(2.), 2.);
+  assert_eq!(clamped.at(4.), 3.);
+
+  let mut switched = line().switch(10., Cont::constant(-1.));
+  assert_eq!(switched.at(9.), 9.);
+  assert_eq!(switched.at(10.), -1.);
+}
+
+const EASINGS: [Interpolation; 21] = [
+  Interpolation::EaseInQuad, Interpolation::EaseOutQuad, Interpolation::EaseInOutQuad,
+  Interpolation::EaseInCubic, Interpolation::EaseOutCubic, Interpolation::EaseInOutCubic,
+  Interpolation::EaseInQuart, Interpolation::EaseOutQuart, Interpolation::EaseInOutQuart,
+  Interpolation::EaseInExpo, Interpolation::EaseOutExpo, Interpolation::EaseInOutExpo,
+  Interpolation::EaseInBack, Interpolation::EaseOutBack, Interpolation::EaseInOutBack,
+  Interpolation::EaseInElastic, Interpolation::EaseOutElastic, Interpolation::EaseInOutElastic,
+  Interpolation::EaseInBounce, Interpolation::EaseOutBounce, Interpolation::EaseInOutBounce
+];
+
+// Sample an easing curve going from 0 to 1 over [0; 1].
+fn sample_easing(easing: Interpolation, t: f32) -> f32 {
+  let p = AnimParam::new(vec![
+    Key::new(0., 0., easing),
+    Key::new(1., 1., Interpolation::Hold)
+  ]);
+
+  Sampler::new().sample(t, &p, true).unwrap()
+}
+
+#[test]
+fn easing_endpoints() {
+  for &easing in &EASINGS {
+    assert_eq!(sample_easing(easing, 0.), 0., "{:?}", easing);
+    assert!((sample_easing(easing, 1. - 1e-6) - 1.).abs() < 2e-3, "{:?}", easing);
+  }
+
+  // in and out curves are symmetric
+  for &easing in &EASINGS {
+    let x = sample_easing(easing, 0.5);
+
+    match easing {
+      Interpolation::EaseInOutQuad | Interpolation::EaseInOutCubic | Interpolation::EaseInOutQuart |
+      Interpolation::EaseInOutExpo | Interpolation::EaseInOutBack | Interpolation::EaseInOutElastic |
+      Interpolation::EaseInOutBounce => assert!((x - 0.5).abs() < 1e-5, "{:?}: {}", easing, x),
+      _ => {}
+    }
+  }
+}
+
+#[test]
+fn easing_monotonic() {
+  let monotonic = [
+    Interpolation::EaseInQuad, Interpolation::EaseOutQuad, Interpolation::EaseInOutQuad,
+    Interpolation::EaseInCubic, Interpolation::EaseOutCubic, Interpolation::EaseInOutCubic,
+    Interpolation::EaseInQuart, Interpolation::EaseOutQuart, Interpolation::EaseInOutQuart,
+    Interpolation::EaseInExpo, Interpolation::EaseOutExpo, Interpolation::EaseInOutExpo
+  ];
+
+  for &easing in &monotonic {
+    let mut last = 0.;
+
+    for i in 1..100 {
+      let x = sample_easing(easing, i as f32 / 100.);
+      assert!(x >= last && x <= 1., "{:?}: {} < {}", easing, x, last);
+      last = x;
+    }
+  }
+
+  // in curves are slow at start, out curves are fast at start
+  assert!(sample_easing(Interpolation::EaseInCubic, 0.25) < 0.25);
+  assert!(sample_easing(Interpolation::EaseOutCubic, 0.25) > 0.25);
+
+  // back and elastic curves overshoot
+  assert!(sample_easing(Interpolation::EaseInBack, 0.2) < 0.);
+  assert!(sample_easing(Interpolation::EaseOutBack, 0.8) > 1.);
+  assert!((1..100).any(|i| sample_easing(Interpolation::EaseOutElastic, i as f32 / 100.) > 1.));
+
+  // bounce curves never leave [0; 1]
+  for &easing in &[Interpolation::EaseInBounce, Interpolation::EaseOutBounce, Interpolation::EaseInOutBounce] {
+    assert!((0..100).all(|i| { let x = sample_easing(easing, i as f32 / 100.); x >= 0. && x <= 1.0001 }), "{:?}", easing);
+  }
+}
+
+// Central finite difference of the sampled curve.
+fn numeric_derivative(p: &AnimParam<f32>, t: f32) -> f32 {
+  let mut sampler = Sampler::new();
+  let h = 1e-3;
+  let a = sampler.sample(t - h, p, true).unwrap();
+  let b = sampler.sample(t + h, p, true).unwrap();
+
+  (b - a) / (2. * h)
+}
+
+#[test]
+fn sampler_derivative() {
+  let mut sampler = Sampler::new();
+
+  let p = AnimParam::new(vec![
+    Key::new(0., 10., Interpolation::Hold),
+    Key::new(2., 20., Interpolation::Hold)
+  ]);
+  assert_eq!(sampler.sample_derivative(1., &p, true), Some(0.));
+  assert_eq!(sampler.sample_second_derivative(1., &p, true), Some(0.));
+
+  let p = AnimParam::new(vec![
+    Key::new(0., 10., Interpolation::Linear),
+    Key::new(2., 20., Interpolation::Linear)
+  ]);
+  assert_eq!(sampler.sample_derivative(1., &p, true), Some(5.));
+  assert_eq!(sampler.sample_second_derivative(1., &p, true), Some(0.));
+  assert_eq!(sampler.sample_derivative(2., &p, true), None);
+
+  let p = AnimParam::new(vec![
+    Key::new(0., 0., Interpolation::Cosine),
+    Key::new(2., 4., Interpolation::Cosine)
+  ]);
+  // the cosine ramp is at its steepest halfway through: π/2 · 4 / 2
+  assert!((sampler.sample_derivative(1., &p, true).unwrap() - std::f32::consts::PI).abs() < 1e-4);
+  assert!(sampler.sample_second_derivative(1., &p, true).unwrap().abs() < 1e-4);
+
+  let interpolations = [Interpolation::CatmullRom, Interpolation::EaseInOutCubic, Interpolation::EaseOutBack];
+  for &interpolation in &interpolations {
+    let p = AnimParam::new(vec![
+      Key::new(0., 0., interpolation),
+      Key::new(1., 3., interpolation),
+      Key::new(3., -1., interpolation),
+      Key::new(4., 2., interpolation)
+    ]);
+
+    for &t in &[0.3, 1.7, 2.2, 3.4] {
+      let d = sampler.sample_derivative(t, &p, true).unwrap();
+      assert!((d - numeric_derivative(&p, t)).abs() < 1e-2, "{:?} at {}: {}", interpolation, t, d);
+    }
+  }
+
+  // easing curves are differentiated analytically; check them against finite differences, away
+  // from the middle of the segments where in-out curves switch halves
+  let easings = [
+    Interpolation::EaseInQuad, Interpolation::EaseOutQuad, Interpolation::EaseInOutQuad,
+    Interpolation::EaseInCubic, Interpolation::EaseOutCubic, Interpolation::EaseInOutCubic,
+    Interpolation::EaseInQuart, Interpolation::EaseOutQuart, Interpolation::EaseInOutQuart,
+    Interpolation::EaseInExpo, Interpolation::EaseOutExpo, Interpolation::EaseInOutExpo,
+    Interpolation::EaseInBack, Interpolation::EaseOutBack, Interpolation::EaseInOutBack,
+    Interpolation::EaseInElastic, Interpolation::EaseOutElastic, Interpolation::EaseInOutElastic,
+    Interpolation::EaseInBounce, Interpolation::EaseOutBounce, Interpolation::EaseInOutBounce
+  ];
+
+  for &interpolation in &easings {
+    let p = AnimParam::new(vec![
+      Key::new(0., 0., interpolation),
+      Key::new(1., 3., interpolation),
+      Key::new(3., -1., interpolation),
+      Key::new(4., 2., interpolation)
+    ]);
+
+    for &t in &[0.3, 1.7, 2.2, 3.4] {
+      let d = sampler.sample_derivative(t, &p, true).unwrap();
+      let d2 = sampler.sample_second_derivative(t, &p, true).unwrap();
+      let h = 1e-3;
+      let numeric_d2 = (sampler.sample_derivative(t + h, &p, true).unwrap() - sampler.sample_derivative(t - h, &p, true).unwrap()) / (2. * h);
+
+      assert!((d - numeric_derivative(&p, t)).abs() < 1e-2 * (1. + d.abs()), "{:?} at {}: {}", interpolation, t, d);
+      assert!((d2 - numeric_d2).abs() < 1e-2 * (1. + d2.abs()), "{:?} at {}: {} instead of {}", interpolation, t, d2, numeric_d2);
+    }
+  }
+
+  // halfway through an in-out curve, where finite differences straddle both halves: 3 · 3
+  let p = AnimParam::new(vec![
+    Key::new(0., 0., Interpolation::EaseInOutCubic),
+    Key::new(1., 3., Interpolation::EaseInOutCubic)
+  ]);
+  assert_eq!(sampler.sample_derivative(0.5, &p, true), Some(9.));
+
+  let p = AnimParam::new(vec![
+    Key::new_bezier(0., 0., Handle::new(0., 0.), Handle::new(0.3, 2.)),
+    Key::new_bezier(1., 1., Handle::new(0.6, -1.), Handle::new(1., 1.))
+  ]);
+  for &t in &[0.2, 0.5, 0.8] {
+    let d = sampler.sample_derivative(t, &p, true).unwrap();
+    assert!((d - numeric_derivative(&p, t)).abs() < 1e-2, "bezier at {}: {}", t, d);
+  }
+}
+
+#[test]
+fn arc_length() {
+  // the first segment is ten times longer than the second, but takes the same time
+  let p = AnimParam::new(vec![
+    Key::new(0., Vector3::new(0., 0., 0.), Interpolation::Linear),
+    Key::new(1., Vector3::new(10., 0., 0.), Interpolation::Linear),
+    Key::new(2., Vector3::new(10., 1., 0.), Interpolation::Linear)
+  ]);
+  let arc = ArcLength::new(&p, 0., 2., 100).unwrap();
+
+  assert!((arc.length() - 11.).abs() < 1e-4);
+  assert!((arc.distance_at_time(1.5) - 10.5).abs() < 1e-4);
+  assert!((arc.time_at_distance(5.) - 0.5).abs() < 1e-4);
+  assert!((arc.time_at_distance(10.5) - 1.5).abs() < 1e-4);
+  assert_eq!(arc.time_at_distance(-1.), 0.);
+  assert_eq!(arc.time_at_distance(12.), 2.);
+  assert!((arc.constant_speed(0.5) - 0.55).abs() < 1e-4);
+  assert!(ArcLength::new(&p, 0., 3., 10).is_none());
+
+  // once retimed, the path is traveled at constant speed
+  let mut cont = arc.clone().cont(p.clone(), 10., 21., Vector3::new(0., 0., 0.));
+  let mut prev = cont.at(10.);
+
+  for i in 1..12 {
+    let v = cont.at(10. + i as f32);
+    let step = ((v.x - prev.x).powi(2) + (v.y - prev.y).powi(2)).sqrt();
+
+    assert!((step - 1.).abs() < 1e-3, "step {} is {}", i, step);
+    prev = v;
+  }
+
+  // an empty range jumps to the end of the path
+  let mut cont = arc.cont(p.clone(), 5., 5., Vector3::new(0., 0., 0.));
+  assert_eq!(cont.at(4.), Vector3::new(0., 0., 0.));
+  assert_eq!(cont.at(5.), Vector3::new(10., 1., 0.));
+
+  // a path of null length stays where it is
+  let still = AnimParam::new(vec![
+    Key::new(0., Vector3::new(1., 2., 3.), Interpolation::Hold),
+    Key::new(1., Vector3::new(1., 2., 3.), Interpolation::Hold)
+  ]);
+  let arc = ArcLength::new(&still, 0., 1., 10).unwrap();
+  assert_eq!(arc.length(), 0.);
+  assert_eq!(arc.cont(still, 0., 1., Vector3::new(0., 0., 0.)).at(0.5), Vector3::new(1., 2., 3.));
+}
+
+#[test]
+fn reduce_keys_within_tolerance() {
+  let samples: Vec<_> = (0..601).map(|i| {
+    let t = i as f32 / 60.;
+    (t, f32::sin(t) + 0.5 * f32::cos(3. * t))
+  }).collect();
+  let mut sampler = Sampler::new();
+
+  for &interpolation in &[Interpolation::Linear, Interpolation::CatmullRom] {
+    let p = reduce_keys(&samples, 1e-2, interpolation);
+
+    for &(t, v) in &samples[..samples.len() - 1] {
+      let r = sampler.sample(t, &p, true).unwrap();
+      assert!((r - v).abs() <= 1e-2, "{:?} at {}: {} instead of {}", interpolation, t, r, v);
+    }
+  }
+
+  let p = reduce_keys(&samples, 1e-2, Interpolation::Linear);
+  assert!(p.into_iter().count() < samples.len() / 5);
+
+  // a straight path collapses to its ends
+  let samples: Vec<_> = (0..100).map(|i| (i as f32, Vector3::new(i as f32, 2. * i as f32, 0.))).collect();
+  let p = reduce_keys(&samples, 1e-3, Interpolation::Linear);
+
+  assert_eq!(p.into_iter().map(|key| key.t).collect::<Vec<_>>(), vec![0., 99.]);
+
+  // orientations turning around Y at constant speed, then back: the turn is the only key needed
+  // in between
+  let samples: Vec<_> = (0..121).map(|i| {
+    let angle = if i <= 60 { i as f32 } else { 120. - i as f32 } * 0.02;
+    (i as f32 / 60., UnitQuaternion::new(Vector3::new(0., angle, 0.)))
+  }).collect();
+  let p = reduce_keys(&samples, 1e-3, Interpolation::Linear);
+
+  assert_eq!(p.into_iter().map(|key| key.t).collect::<Vec<_>>(), vec![0., 1., 2.]);
+
+  for &(t, q) in &samples[..samples.len() - 1] {
+    let r = sampler.sample(t, &p, true).unwrap();
+    assert!(Metric::distance(&r, &q) <= 1e-3, "at {}: {:?} instead of {:?}", t, r, q);
+  }
+}
+
+#[test]
+fn tempo_map() {
+  // 120 BPM in 4/4 starting at 1s, then 60 BPM from the third bar
+  let tempo = TempoMap::new(120., TimeSignature::new(4, 4)).with_offset(1.).with_tempo_change(MusicalTime::new(3, 1, 0), 60.);
+
+  assert_eq!(tempo.to_seconds(MusicalTime::new(1, 1, 0)), 1.);
+  assert_eq!(tempo.to_seconds(MusicalTime::new(1, 2, 240)), 1.75);
+  assert_eq!(tempo.to_seconds(MusicalTime::new(2, 1, 0)), 3.);
+  assert_eq!(tempo.to_seconds(MusicalTime::new(3, 1, 0)), 5.);
+  assert_eq!(tempo.to_seconds(MusicalTime::new(3, 3, 0)), 7.);
+  assert_eq!(tempo.to_seconds(MusicalTime::new(0, 4, 0)), 0.5);
+  assert_eq!(tempo.bpm_at(4.), 120.);
+  assert_eq!(tempo.bpm_at(6.), 60.);
+
+  assert_eq!(tempo.to_musical(1.75), MusicalTime::new(1, 2, 240));
+  assert_eq!(tempo.to_musical(7.), MusicalTime::new(3, 3, 0));
+  assert_eq!(tempo.to_musical(0.5), MusicalTime::new(0, 4, 0));
+
+  assert_eq!(tempo.quantize(1.6, 1), 1.5);
+  assert_eq!(tempo.quantize(1.6, 2), 1.5);
+  assert_eq!(tempo.quantize(1.6, 4), 1.625);
+  assert_eq!(tempo.quantize(6.3, 1), 6.);
+
+  let p = AnimParam::new(vec![
+    Key::new(1.1, 0., Interpolation::Linear),
+    Key::new(2.9, 1., Interpolation::Linear)
+  ]);
+  let q = tempo.quantize_keys(&p, 1);
+  assert_eq!(q.into_iter().map(|key| key.t).collect::<Vec<_>>(), vec![1., 3.]);
+
+  let p = p.with_extrapolation(Some(Extrapolation::Constant), Some(Extrapolation::Linear));
+  let q = tempo.quantize_keys(&p, 1);
+  assert_eq!(q.pre_extrapolation(), Some(Extrapolation::Constant));
+  assert_eq!(q.post_extrapolation(), Some(Extrapolation::Linear));
+
+  // tempi count quarter notes: eighth note beats go twice as fast
+  let tempo = TempoMap::new(120., TimeSignature::new(6, 8));
+
+  assert_eq!(tempo.to_seconds(MusicalTime::new(1, 2, 0)), 0.25);
+  assert_eq!(tempo.to_seconds(MusicalTime::new(2, 1, 0)), 1.5);
+  assert_eq!(tempo.to_musical(1.75), MusicalTime::new(2, 2, 0));
+
+  assert_eq!(MusicalTime::parse("12:3"), Some(MusicalTime::new(12, 3, 0)));
+  assert_eq!(MusicalTime::parse("-1:2:100"), Some(MusicalTime::new(-1, 2, 100)));
+  assert_eq!(MusicalTime::parse("1:0"), None);
+  assert_eq!(MusicalTime::parse("1:1:480"), None);
+  assert_eq!(MusicalTime::parse("4.5"), None);
+}
+
+#[test]
+fn keyframes_musical_time() {
+  let src = "\
+tempo 120 4/4 offset 1
+tempo 60 at 3:1
+
+track flash f32
+  1:1     linear 0
+  1:2:240 linear 1
+  3:3     hold   2
+";
+  let keyframes = Keyframes::parse("musical.keys", src).unwrap();
+  let times: Vec<_> = keyframes.get::<f32>("flash").unwrap().into_iter().map(|key| key.t).collect();
+
+  assert_eq!(times, vec![1., 1.75, 7.]);
+  assert_eq!(keyframes.tempo().unwrap().bpm(), 120.);
+
+  // the tempo survives a round trip
+  let written = keyframes.to_string();
+  let reparsed = Keyframes::parse("written.keys", &written).unwrap();
+  assert_eq!(reparsed.tempo(), keyframes.tempo());
+  assert_eq!(reparsed.to_string(), written);
+
+  // times are written back in musical time, unless they fall between ticks, even barely
+  let mut keyframes = keyframes;
+  let mut flash = keyframes.get::<f32>("flash").unwrap().clone();
+  flash.insert(Key::new(1.0001, 0.5, Interpolation::Linear));
+  flash.insert(Key::new(1.75001, 0.5, Interpolation::Linear));
+  keyframes.insert("flash", TrackValue::into_keys(flash));
+
+  let written = keyframes.to_string();
+  let keys: Vec<_> = written.lines().skip_while(|line| !line.starts_with("track")).skip(1).map(|line| line.split_whitespace().next().unwrap()).collect();
+  assert_eq!(keys, vec!["1:1", "1.0001", "1:2:240", "1.75001", "3:3"]);
+
+  let reparsed = Keyframes::parse("written.keys", &written).unwrap();
+  let times = |keyframes: &Keyframes| keyframes.get::<f32>("flash").unwrap().into_iter().map(|key| key.t).collect::<Vec<_>>();
+  assert_eq!(times(&reparsed), times(&keyframes));
+
+  let errors = [
+    ("track a f32\n  1:1 hold 0\n", 2, 3),
+    ("tempo 120 4/4\ntrack a f32\n  1:0 hold 0\n", 3, 3),
+    ("tempo 120 at 2:1\n", 1, 1),
+    ("tempo 120 4/0\n", 1, 11),
+    ("tempo 120 4/4\ntempo 130 3/4\n", 2, 1),
+    ("track a f32\ntempo 120 4/4\n", 2, 1)
+  ];
+
+  for &(src, line, column) in &errors {
+    let e = Keyframes::parse("bad.keys", src).unwrap_err();
+    assert_eq!((e.line, e.column), (line, column), "{}", e);
+  }
+}
+
+#[test]
+fn sampler_extrapolation() {
+  let mut sampler = Sampler::new();
+  let keys = vec![
+    Key::new(1., 0., Interpolation::Linear),
+    Key::new(2., 2., Interpolation::Linear),
+    Key::new(3., 3., Interpolation::Linear)
+  ];
+  let sample = |sampler: &mut Sampler, p: &AnimParam<f32>, t: f32| sampler.sample(t, p, true);
+
+  let p = AnimParam::new(keys.clone());
+  assert_eq!(sample(&mut sampler, &p, 0.), None);
+  assert_eq!(sample(&mut sampler, &p, 3.), None);
+
+  let p = AnimParam::new(keys.clone()).with_extrapolation(Some(Extrapolation::Constant), Some(Extrapolation::Constant));
+  assert_eq!(sample(&mut sampler, &p, -10.), Some(0.));
+  assert_eq!(sample(&mut sampler, &p, 3.), Some(3.));
+  assert_eq!(sample(&mut sampler, &p, 1000.), Some(3.));
+
+  let p = AnimParam::new(keys.clone()).with_extrapolation(Some(Extrapolation::Linear), Some(Extrapolation::Linear));
+  assert_eq!(sample(&mut sampler, &p, 0.), Some(-2.));
+  assert_eq!(sample(&mut sampler, &p, 5.), Some(5.));
+
+  let p = AnimParam::new(keys.clone()).with_extrapolation(Some(Extrapolation::Cycle), Some(Extrapolation::Cycle));
+  assert_eq!(sample(&mut sampler, &p, 3.), Some(0.));
+  assert_eq!(sample(&mut sampler, &p, 4.5), Some(2.5));
+  assert_eq!(sample(&mut sampler, &p, -0.5), Some(1.));
+
+  let p = AnimParam::new(keys.clone()).with_extrapolation(Some(Extrapolation::CycleOffset), Some(Extrapolation::CycleOffset));
+  assert_eq!(sample(&mut sampler, &p, 3.), Some(3.));
+  assert_eq!(sample(&mut sampler, &p, 4.5), Some(5.5));
+  assert_eq!(sample(&mut sampler, &p, 6.5), Some(8.5));
+  assert_eq!(sample(&mut sampler, &p, -0.5), Some(-2.));
+
+  let p = AnimParam::new(keys.clone()).with_extrapolation(Some(Extrapolation::PingPong), Some(Extrapolation::PingPong));
+  assert_eq!(sample(&mut sampler, &p, 3.), Some(3.));
+  assert_eq!(sample(&mut sampler, &p, 3.5), Some(2.5));
+  assert_eq!(sample(&mut sampler, &p, 4.5), Some(1.));
+  assert_eq!(sample(&mut sampler, &p, 5.5), Some(1.));
+  assert_eq!(sample(&mut sampler, &p, 0.5), Some(1.));
+  assert_eq!(sampler.sample_derivative(3.5, &p, true), Some(-1.));
+
+  // single keys are held whatever the mode
+  let p = AnimParam::new(vec![Key::new(1., 4., Interpolation::Linear)]).with_extrapolation(Some(Extrapolation::Linear), Some(Extrapolation::PingPong));
+  assert_eq!(sample(&mut sampler, &p, 0.), Some(4.));
+  assert_eq!(sample(&mut sampler, &p, 2.), Some(4.));
+
+  // jumps at the ends give no slope: the outer keys are held
+  let p = AnimParam::new(vec![
+    Key::new(1., 0., Interpolation::Hold),
+    Key::new(1., 1., Interpolation::Linear),
+    Key::new(2., 2., Interpolation::Hold),
+    Key::new(2., 3., Interpolation::Hold)
+  ]).with_extrapolation(Some(Extrapolation::Linear), Some(Extrapolation::Linear));
+  assert_eq!(sample(&mut sampler, &p, 0.), Some(0.));
+  assert_eq!(sample(&mut sampler, &p, 3.), Some(3.));
+  assert_eq!(sampler.sample_derivative(3., &p, true), Some(0.));
+
+  // orientations keep on rotating with offset cycles
+  let p = AnimParam::new(vec![
+    Key::new(0., y_rotation(0.), Interpolation::Linear),
+    Key::new(1., y_rotation(0.5), Interpolation::Linear)
+  ]).with_extrapolation(None, Some(Extrapolation::CycleOffset));
+  let q = sampler.sample(2.5, &p, true).unwrap();
+  assert!(angle_between(q, y_rotation(1.25)) < 1e-4);
+
+  let keyframes = Keyframes::parse("extrapolation.keys", "track a f32 pre cycle post ping-pong\n  0 linear 0\n  1 linear 1\n").unwrap();
+  let a = keyframes.get::<f32>("a").unwrap();
+  assert_eq!((a.pre_extrapolation(), a.post_extrapolation()), (Some(Extrapolation::Cycle), Some(Extrapolation::PingPong)));
+  assert!(keyframes.to_string().starts_with("track a f32 pre cycle post ping-pong\n"));
+  assert!(Keyframes::parse("bad.keys", "track a f32 post bounce\n").is_err());
+  assert!(Keyframes::parse("bad.keys", "track a f32 post constant post linear\n").is_err());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fog {
+  color: Vector3<f32>,
+  density: f32
+}
+
+impl_interpolate!(Fog { color, density });
+
+#[test]
+fn interpolate_compound_types() {
+  let mut sampler = Sampler::new();
+
+  let p = AnimParam::new(vec![
+    Key::new(0., [0., 10., -2.], Interpolation::Linear),
+    Key::new(1., [1., 20., -4.], Interpolation::Linear)
+  ]);
+  assert_eq!(sampler.sample(0.5, &p, true), Some([0.5, 15., -3.]));
+
+  let p = AnimParam::new(vec![
+    Key::new(0., (0., Vector3::new(0., 0., 0.)), Interpolation::Linear),
+    Key::new(2., (4., Vector3::new(2., 4., 6.)), Interpolation::Linear)
+  ]).with_extrapolation(None, Some(Extrapolation::CycleOffset));
+  assert_eq!(sampler.sample(1., &p, true), Some((2., Vector3::new(1., 2., 3.))));
+  assert_eq!(sampler.sample(3., &p, true), Some((6., Vector3::new(3., 6., 9.))));
+
+  let p = AnimParam::new(vec![
+    Key::new(0., Fog { color: Vector3::new(0., 0., 0.), density: 1. }, Interpolation::Linear),
+    Key::new(1., Fog { color: Vector3::new(1., 1., 1.), density: 0. }, Interpolation::Linear)
+  ]);
+  assert_eq!(sampler.sample(0.25, &p, true), Some(Fog { color: Vector3::new(0.25, 0.25, 0.25), density: 0.75 }));
+
+  let a = Transform::new(Vector3::new(0., 0., 0.), y_rotation(0.), Scale::uni(1.));
+  let b = Transform::new(Vector3::new(2., 0., 0.), y_rotation(1.), Scale::uni(3.));
+  let p = AnimParam::new(vec![
+    Key::new(0., a, Interpolation::Linear),
+    Key::new(1., b, Interpolation::Linear)
+  ]);
+  let mid = sampler.sample(0.5, &p, true).unwrap();
+
+  assert_eq!(mid.translation, Vector3::new(1., 0., 0.));
+  assert!(angle_between(mid.orientation, y_rotation(0.5)) < 1e-5);
+  assert_eq!((mid.scale.x, mid.scale.y, mid.scale.z), (2., 2., 2.));
+}
+
+#[test]
+fn step_track() {
+  #[derive(Clone, Copy, Debug, PartialEq)]
+  enum Logo { None, Group, Party }
+
+  let step = Step::new(Logo::None, vec![(10., Logo::Party), (2., Logo::Group), (10., Logo::None)]);
+
+  assert_eq!(step.at(0.), Logo::None);
+  assert_eq!(step.at(2.), Logo::Group);
+  assert_eq!(step.at(9.99), Logo::Group);
+  // the last of the moments at the same time wins
+  assert_eq!(step.at(10.), Logo::None);
+  assert_eq!(step.at(100.), Logo::None);
+  assert_eq!(step.index_at(1.), None);
+  assert_eq!(step.index_at(5.), Some(0));
+
+  let mut cont = Cont::from_discrete(0, vec![(3., 30), (1., 10), (2., 20)]);
+  assert_eq!(cont.at(0.5), 0);
+  assert_eq!(cont.at(1.5), 10);
+  assert_eq!(cont.at(2.), 20);
+  assert_eq!(cont.at(7.), 30);
+}
+
+#[test]
+fn blend_layers() {
+  let take_a = AnimParam::new(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(10., 10., Interpolation::Linear)
+  ]).into_cont(0.);
+  let take_b = Cont::constant(20.);
+  // crossfade from the first take to the second one between 2s and 4s
+  let fade = AnimParam::new(vec![
+    Key::new(2., 0., Interpolation::Linear),
+    Key::new(4., 1., Interpolation::Linear)
+  ]).with_extrapolation(Some(Extrapolation::Constant), Some(Extrapolation::Constant)).into_cont(0.);
+  let mut blend = Blend::new(take_a).layer(take_b, fade, BlendMode::Override);
+
+  assert_eq!(blend.at(1.), 1.);
+  assert_eq!(blend.at(3.), 11.5);
+  assert_eq!(blend.at(5.), 20.);
+
+  // additive shake on top of a base motion
+  let base = Cont::new(|t| Vector3::new(t, 0., 0.));
+  let shake = Cont::new(|t: f32| Vector3::new(1., 1. + t, 1.));
+  let mut blend = Blend::new(base).layer(shake, Cont::constant(0.5), BlendMode::Additive(Vector3::new(1., 1., 1.)));
+
+  assert_eq!(blend.at(2.), Vector3::new(2., 1., 0.));
+
+  // orientations blend along the sphere
+  let mut blend = Blend::new(Cont::constant(y_rotation(0.)))
+    .layer(Cont::constant(y_rotation(2.)), Cont::constant(0.25), BlendMode::Override)
+    .layer(Cont::constant(y_rotation(1.)), Cont::constant(1.), BlendMode::Additive(y_rotation(0.)));
+  let q = blend.at(0.);
+
+  assert!(angle_between(q, y_rotation(1.5)) < 1e-5);
+  assert!((q.quaternion().w.powi(2) + q.quaternion().j.powi(2) - 1.).abs() < 1e-5);
+}
+
+#[test]
+fn spring_follower() {
+  // a step from 0 to 1 at 1s
+  let target = || Step::new(0., vec![(1., 1.)]).into_cont();
+  let mut spring = Spring::critically_damped(target(), 100.);
+
+  assert_eq!(spring.at(0.5), 0.);
+  assert!(spring.at(1.05) > 0.);
+  assert!(spring.at(1.05) < 0.5);
+  assert!((spring.at(3.) - 1.).abs() < 1e-3);
+
+  // critically damped springs never overshoot
+  for i in 0..300 {
+    assert!(spring.at(1. + i as f32 / 100.) <= 1.);
+  }
+
+  // seeking backwards gives the same values as playing forward, and as a fresh spring
+  let times: Vec<_> = (0..200).map(|i| i as f32 * 0.037).collect();
+  let forward: Vec<_> = times.iter().map(|&t| spring.at(t)).collect();
+  let backward: Vec<_> = times.iter().rev().map(|&t| spring.at(t)).collect();
+  let mut fresh = Spring::critically_damped(target(), 100.);
+
+  assert_eq!(forward, backward.into_iter().rev().collect::<Vec<_>>());
+  assert_eq!(spring.at(4.2), fresh.at(4.2));
+
+  // vectors are smoothed component-wise, lagging behind a moving target
+  let mut spring = Spring::critically_damped(Cont::new(|t| Vector3::new(t, 2. * t, 0.)), 100.);
+  let v = spring.at(1.);
+  assert!(v.x > 0.5 && v.x < 1.);
+  assert!((v.y - 2. * v.x).abs() < 1e-4);
+  assert_eq!(v.z, 0.);
+}
+
+fn eval_expr(src: &str, t: f32) -> f32 {
+  Expr::parse(src).unwrap().eval(t, &mut |name, _| panic!("unexpected reference {}", name))
+}
+
+#[test]
+fn expressions() {
+  assert_eq!(eval_expr("1 + 2 * 3", 0.), 7.);
+  assert_eq!(eval_expr("(1 + 2) * 3", 0.), 9.);
+  assert_eq!(eval_expr("-2^2", 0.), -4.);
+  assert_eq!(eval_expr("2^3^2", 0.), 512.);
+  assert_eq!(eval_expr("7 % 4 - t / 2", 3.), 1.5);
+  assert_eq!(eval_expr("fract(t) + abs(-2)", 1.25), 2.25);
+  assert_eq!(eval_expr("clamp(t, 0, 1) + mix(10, 20, 0.25)", 4.), 13.5);
+  assert_eq!(eval_expr("smoothstep(0, 2, 1) + step(1, t)", 0.5), 0.5);
+  assert_eq!(eval_expr("sin(pi / 2) * cos(0)", 0.), 1.);
+  assert_eq!(eval_expr("noise(3)", 0.), eval_expr("noise(t)", 3.));
+  assert!(eval_expr("noise(t)", 0.37) >= 0. && eval_expr("noise(t)", 0.37) <= 1.);
+
+  let errors = [
+    ("1 +", 4, "unexpected end of expression"),
+    ("sin(1, 2)", 1, "sin expects 1 arguments, found 2"),
+    ("foo(1)", 1, "unknown function foo"),
+    ("(1 + 2", 7, "expected )"),
+    ("2 $ 3", 3, "unexpected character $"),
+    ("1 2", 3, "unexpected 2")
+  ];
+
+  for &(src, column, reason) in &errors {
+    assert_eq!(Expr::parse(src).unwrap_err(), ExprError { column: column, reason: reason.to_owned() });
+  }
+
+  let expr = Expr::parse("base + amp * sin(t) + amp").unwrap();
+  assert_eq!(expr.references().iter().map(|&(column, ref name)| (column, &name[..])).collect::<Vec<_>>(),
+             vec![(1, "base"), (8, "amp"), (23, "amp")]);
+
+  let mut cont = expr.clone().into_cont(vec![
+    ("amp".to_owned(), Cont::constant(2.)),
+    ("base".to_owned(), Cont::new(|t| t * 10.))
+  ]).unwrap();
+  assert_eq!(cont.at(0.), 2.);
+  assert_eq!(cont.at(1.), 10. + 2. * f32::sin(1.) + 2.);
+
+  assert_eq!(expr.into_cont(vec![("amp".to_owned(), Cont::constant(2.))]).err().unwrap(),
+             ExprError { column: 1, reason: "unknown name base".to_owned() });
+}
+
+#[test]
+fn keyframes_expressions() {
+  let src = "\
+const speed 2
+expr pulse fract(t * speed) * flash # a comment
+expr double pulse * 2
+
+track flash f32
+  0 linear 0
+  1 linear 1
+";
+  let keyframes = Keyframes::parse("exprs.keys", src).unwrap();
+
+  assert_eq!(keyframes.value("speed", 0.), Some(2.));
+  assert_eq!(keyframes.value("flash", 0.5), Some(0.5));
+  assert_eq!(keyframes.value("pulse", 0.25), Some(0.125));
+  assert_eq!(keyframes.value("double", 0.25), Some(0.25));
+  // flash isn’t defined after its last key
+  assert_eq!(keyframes.value("pulse", 2.), None);
+  assert_eq!(keyframes.value("nope", 0.), None);
+
+  let written = keyframes.to_string();
+  assert!(written.starts_with("const speed 2\nexpr pulse fract(t * speed) * flash\nexpr double pulse * 2\n\ntrack flash f32\n"));
+  assert_eq!(Keyframes::parse("written.keys", &written).unwrap().to_string(), written);
+
+  let mut timeline = Timeline::from_keyframes(keyframes);
+  assert_eq!(timeline.sample(0.25).get::<f32>("double"), Some(0.25));
+
+  // keys giving the overall shape, an expression adding details on top of them
+  let src = "\
+expr glow flash * (0.5 + 0.5 * step(0.5, fract(t * 4)))
+
+track flash f32 post constant
+  0 linear 0
+  2 hold   1
+";
+  let keyframes = Keyframes::parse("glow.keys", src).unwrap();
+
+  assert_eq!(keyframes.value("glow", 1.), Some(0.25));
+  assert_eq!(keyframes.value("glow", 1.125), Some(0.5625));
+  assert_eq!(keyframes.value("glow", 10.), Some(0.5));
+  assert_eq!(keyframes.value("glow", 10.125), Some(1.));
+
+  let errors = [
+    ("expr a 1 + b\n", 1, 12, "unknown name b"),
+    ("expr a b\nexpr b 2 * a\n", 2, 12, "a depends on itself"),
+    ("expr a sin(\n", 1, 12, "unexpected end of expression"),
+    ("track v vec2\n  0 hold 0 0\nexpr a v\n", 3, 8, "v is a vec2 track, expected f32"),
+    ("const a 1\ntrack a f32\n", 2, 7, "a is already defined"),
+    ("const a x\n", 1, 9, "expected a number, found x")
+  ];
+
+  for &(src, line, column, reason) in &errors {
+    let e = Keyframes::parse("bad.keys", src).unwrap_err();
+    assert_eq!((e.line, e.column, &e.reason[..]), (line, column, reason));
+  }
+}
+
+#[test]
+fn edit_keys() {
+  let mut sampler = Sampler::new();
+  let mut p = AnimParam::new(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(1., 1., Interpolation::Linear),
+    Key::new(2., 2., Interpolation::Linear),
+    Key::new(3., 3., Interpolation::Linear)
+  ]);
+
+  // put the cursor on the last segment
+  assert_eq!(sampler.sample(2.5, &p, false), Some(2.5));
+
+  assert_eq!(p.insert(Key::new(1.5, 5., Interpolation::Hold)), 2);
+  assert_eq!(p.insert(Key::new(1., -1., Interpolation::Linear)), 2);
+  assert_eq!(p.len(), 6);
+  assert_eq!(p.keys().iter().map(|key| key.t).collect::<Vec<_>>(), vec![0., 1., 1., 1.5, 2., 3.]);
+  assert_eq!(sampler.sample(1.75, &p, false), Some(5.));
+
+  assert_eq!(p.remove(3).map(|key| key.value), Some(5.));
+  assert_eq!(p.remove(3).map(|key| key.value), Some(2.));
+  assert_eq!(p.remove(3).map(|key| key.value), Some(3.));
+  assert!(p.remove(3).is_none());
+
+  // the cursor pointed past the remaining keys
+  assert_eq!(sampler.sample(0.5, &p, false), Some(0.5));
+  assert_eq!(sampler.sample(1.5, &p, false), None);
+
+  assert_eq!(p.move_key(0, 2.), Some(2));
+  assert_eq!(p.keys().iter().map(|key| (key.t, key.value)).collect::<Vec<_>>(), vec![(1., 1.), (1., -1.), (2., 0.)]);
+  assert_eq!(sampler.sample(1.5, &p, false), Some(-0.5));
+
+  assert!(p.set_value(2, 4.));
+  assert!(p.set_interpolation(1, Interpolation::Hold));
+  assert!(!p.set_value(3, 0.));
+  assert_eq!(sampler.sample(1.5, &p, false), Some(-1.));
+  assert_eq!(p.get(2).map(|key| key.value), Some(4.));
+
+  // handles follow their key
+  let mut p = AnimParam::new(vec![
+    Key::new_bezier(0., 0., Handle::new(-0.5, 0.), Handle::new(0.5, 1.)),
+    Key::new(1., 1., Interpolation::Linear)
+  ]);
+
+  assert_eq!(p.move_key(0, 2.), Some(1));
+  assert!(p.set_value(1, 2.));
+  assert_eq!(p.get(1).unwrap().handles, Some((Handle::new(1.5, 2.), Handle::new(2.5, 3.))));
+}
+
+#[test]
+fn baked_tables() {
+  let p = AnimParam::new(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(1., 2., Interpolation::Cosine),
+    Key::new(2., 0., Interpolation::Linear)
+  ]);
+  let baked = Baked::from_param(&p, 10.).unwrap();
+
+  assert_eq!(baked.samples().len(), 21);
+  assert_eq!((baked.start(), baked.end()), (0., 2.));
+  assert_eq!(baked.at(0.5), 1.);
+  assert_eq!(baked.at(-1.), 0.);
+  assert_eq!(baked.at(2.), 0.);
+  assert_eq!(baked.at(5.), 0.);
+  assert!(baked.max_error(&AnimParam::new(vec![Key::new(0., 0., Interpolation::Linear), Key::new(1., 2., Interpolation::Linear)]), 4) < 1e-5);
+
+  // finer tables are closer to the cosine curve
+  let error = baked.max_error(&p, 8);
+  let finer_error = Baked::from_param(&p, 100.).unwrap().max_error(&p, 8);
+  assert!(error > 0. && error < 0.02, "{}", error);
+  assert!(finer_error < error / 10.);
+
+  assert!(Baked::new(&p, 1., 3., 10.).is_none());
+  assert!(Baked::new(&p, 1., 1., 10.).is_none());
+
+  let p = AnimParam::new(vec![
+    Key::new(0., Vector3::new(0., 1., 2.), Interpolation::Linear),
+    Key::new(1., Vector3::new(1., 2., 3.), Interpolation::Linear)
+  ]);
+  let baked = Baked::from_param(&p, 2.).unwrap();
+  assert_eq!(baked.to_raw(), vec![0., 1., 2., 0.5, 1.5, 2.5, 1., 2., 3.]);
+
+  // a rate that doesn’t divide the range: samples are spread evenly up to the end
+  let p = AnimParam::new(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(1., 1., Interpolation::Linear)
+  ]);
+  let baked = Baked::new(&p, 0., 1., 2.5).unwrap();
+
+  assert_eq!(baked.samples().len(), 4);
+  assert_eq!(baked.end(), 1.);
+  assert_eq!(baked.rate(), 3.);
+  assert!((baked.at(0.9) - 0.9).abs() < 1e-6);
+  assert!(baked.max_error(&p, 8) < 1e-5);
+}
+
+#[test]
+fn time_types() {
+  let mut sampler = Sampler::new();
+
+  // frames
+  let frames: AnimParam<f32, i64> = AnimParam::new(vec![
+    Key::new(0, 0., Interpolation::Linear),
+    Key::new(60, 1., Interpolation::CatmullRom),
+    Key::new(120, 3., Interpolation::Linear)
+  ]).with_extrapolation(None, Some(Extrapolation::Cycle));
+
+  assert_eq!(sampler.sample(30, &frames, true), Some(0.5));
+  assert_eq!(sampler.sample(60, &frames, false), Some(1.));
+  assert_eq!(sampler.sample(150, &frames, false), Some(0.5));
+  assert_eq!(sampler.sample_derivative(30, &frames, true), Some(1. / 60.));
+  assert_eq!(frames.time_range(), Some((0, 120)));
+
+  let mut cont = frames.into_cont(0.).map(|x| x * 2.);
+  assert_eq!(cont.at(60), 2.);
+
+  // an hour in, f32 can’t tell 1/60th of a second apart anymore, but f64 can
+  let start = 3600.;
+  let precise: AnimParam<f32, f64> = AnimParam::new(vec![
+    Key::new(start, 0., Interpolation::Linear),
+    Key::new(start + 1. / 60., 1., Interpolation::Linear)
+  ]);
+  let value = sampler.sample(start + 1. / 120., &precise, true).unwrap();
+
+  assert!((value - 0.5).abs() < 1e-3);
+
+  // moving keys shifts their handles by a whole number of frames
+  let mut frames = AnimParam::new(vec![
+    Key::new_bezier(0i64, 0., Handle::new(-10, 0.), Handle::new(10, 1.)),
+    Key::new(30, 1., Interpolation::Linear)
+  ]);
+
+  assert_eq!(frames.move_key(0, 5), Some(0));
+  assert_eq!(frames.get(0).and_then(|key| key.handles).map(|(i, o)| (i.t, o.t)), Some((-5, 15)));
+
+  // continuous values over frames
+  let mut doubled = Cont::from_fn(|frame: i64| frame * 2).remap(|frame| frame + 1);
+  assert_eq!(doubled.at(3), 8);
+}
+
+animation_struct!(Lights, animation_lights, "lights.kf", {
+  flash: f32 = 0. => "flash";
+  tint: Vector3<f32> = Vector3::new(1., 1., 1.) => "tint" (None, Some(Extrapolation::Constant));
+  dim: f32 = 1. => [
+    (0., 1., Interpolation::Linear),
+    (2., 0., Interpolation::Hold)
+  ];
+});
+
+animation_struct!(Fade, animation_fade, {
+  alpha: f32 = 0. => [
+    (1., 0., Interpolation::Linear),
+    (2., 1., Interpolation::Linear)
+  ] (Some(Extrapolation::Constant), None);
+  t: f32 = 5. => []
+});
+
+simple_animation!(animation_flash, f32, 0., "lights.kf", "flash");
+
+// no imports here: the macros must name everything they use
+mod unimported {
+  simple_animation!(animation_ramp, f32, 0., [
+    (0., 0., ::ion::anim::Interpolation::Linear),
+    (1., 2., ::ion::anim::Interpolation::Linear)
+  ]);
+
+  pub fn ramp() -> f32 {
+    animation_ramp().at(0.5)
+  }
+}
+
+#[test]
+fn animation_structs() {
+  let mut lights = animation_lights();
+
+  let Lights { flash, tint, dim } = lights.at(0.5);
+  assert_eq!((flash, tint, dim), (0.5, Vector3::new(1., 1., 1.), 0.75));
+
+  let Lights { flash, tint, dim } = lights.at(4.5);
+  assert_eq!((flash, tint, dim), (0.5, Vector3::new(0., 1., 0.), 1.));
+  assert_eq!(lights.at(2.5).tint, Vector3::new(0., 0.5, 0.5));
+
+  let mut fade = animation_fade();
+  assert_eq!((fade.at(0.).alpha, fade.at(1.5).alpha, fade.at(3.).alpha), (0., 0.5, 0.));
+  assert_eq!(fade.at(1.5).t, 5.);
+
+  assert_eq!(animation_flash().at(3.25), 0.75);
+  assert_eq!(unimported::ramp(), 1.);
+}
+
+#[test]
+fn fly_camera_look() {
+  let mut fly = FlyCamera::new(Transform::default());
+
+  // turning around and looking up and down in any order never tilts the horizon
+  for &rel in &[[40., 0.], [0., 30.], [-70., 0.], [0., -50.], [25., 25.], [60., -10.]] {
+    fly.look(rel);
+
+    let side = fly.transform.orientation.inverse_rotate(&X_AXIS);
+    assert!(side.y.abs() < 1e-5, "{:?}", side);
+  }
+}
+
+#[test]
+fn camera_capture() {
+  let mut fly = FlyCamera::new(Transform::default()).with_speed(2.);
+  let mut capture = CameraCapture::new(Interpolation::Linear);
+
+  fly.fly(FlyMove::Forward);
+  assert_eq!(fly.transform.translation, Vector3::new(0., 0., 2.));
+  assert_eq!(capture.capture(1., &fly.transform), 0);
+
+  fly.fly(FlyMove::Left);
+  assert_eq!(capture.capture(0.5, &fly.transform), 0);
+
+  // capturing again at the same time replaces the keys
+  fly.fly(FlyMove::Up);
+  assert_eq!(capture.capture(1.0002, &fly.transform), 1);
+  assert_eq!(capture.len(), 2);
+  assert_eq!(capture.capture(1., &fly.transform), 1);
+  assert_eq!(capture.len(), 2);
+  assert_eq!(capture.positions().get(1).map(|key| key.value), Some(Vector3::new(2., -2., 2.)));
+
+  let src = capture.to_keyframes("camera").to_string();
+  let keyframes = Keyframes::parse("camera.kf", &src).unwrap();
+  let positions = keyframes.get::<Vector3<f32>>("camera_position").unwrap();
+
+  assert_eq!(positions.keys(), capture.positions().keys());
+  assert_eq!(keyframes.get::<UnitQuaternion<f32>>("camera_orientation").map(|param| param.len()), Some(2));
+
+  let rust = capture.to_rust();
+  assert!(rust.contains("Key::new(0.5, Position::new(2.0, 0.0, 2.0), Interpolation::Linear),"));
+  assert!(rust.contains("Key::new(1.0, Orientation::new_with_quaternion(Quaternion::new(1.0, 0.0, 0.0, 0.0)), Interpolation::Linear),"));
+
+  assert!(capture.remove(0));
+  assert_eq!(capture.orientations().len(), 1);
+}
+
+#[test]
+fn camera_rig() {
+  let close = |a: Vector3<f32>, b: Vector3<f32>| nalgebra::norm(&(a - b)) < 1e-4;
+
+  // the target ends up straight ahead, on the -Z axis of the view
+  let eyes = [Vector3::new(0., 0., 5.), Vector3::new(3., 2., -1.), Vector3::new(-4., 0.5, 0.), Vector3::new(1., 10., 0.)];
+  let target = Vector3::new(1., 0., 0.);
+
+  for eye in &eyes {
+    let q = look_at(*eye, target, Vector3::new(0., 1., 0.));
+    let dist = nalgebra::norm(&(target - *eye));
+
+    assert!(close(q.rotate(&(target - *eye)), Vector3::new(0., 0., -dist)));
+  }
+
+  // the top of the view points up
+  let q = look_at(Vector3::new(3., 2., -1.), target, Vector3::new(0., 1., 0.));
+  assert!(q.rotate(&Vector3::new(0., 1., 0.)).y > 0.);
+
+  // looking straight down still works
+  let q = look_at(Vector3::new(0., 5., 0.), Vector3::new(0., 0., 0.), Vector3::new(0., 1., 0.));
+  assert!(close(q.rotate(&Vector3::new(0., -5., 0.)), Vector3::new(0., 0., -5.)));
+
+  let eye = AnimParam::new(vec![
+    Key::new(0., Vector3::new(0., 0., 5.), Interpolation::Linear),
+    Key::new(1., Vector3::new(5., 0., 0.), Interpolation::Linear)
+  ]).with_extrapolation(None, Some(Extrapolation::Constant));
+  let roll = Cont::new(|t| t * std::f32::consts::FRAC_PI_2);
+  let mut camera = CameraRig::new(eye.into_cont(Vector3::new(0., 0., 0.)), Cont::constant(Vector3::new(0., 0., 0.)))
+    .with_roll(roll)
+    .into_cont("projection");
+
+  let entity = camera.at(1.);
+  assert_eq!(entity.object, "projection");
+  assert!(close(entity.transform.translation, Vector3::new(-5., 0., 0.)));
+
+  // the view is rolled by a quarter turn: the world up axis is now on the side
+  let up = entity.transform.orientation.rotate(&Vector3::new(0., 1., 0.));
+  let forward = entity.transform.orientation.rotate(&Vector3::new(-5., 0., 0.));
+  assert!(close(forward, Vector3::new(0., 0., -5.)));
+  assert!(up.y.abs() < 1e-4 && (up.x.abs() - 1.).abs() < 1e-4);
+}
+
+#[test]
+fn camera_shake() {
+  let times: Vec<f32> = (0..200).map(|i| i as f32 * 0.037).collect();
+  let mut shake = Shake::new(7).with_amplitude(Cont::constant(0.5));
+  let offsets: Vec<_> = times.iter().map(|&t| shake.at(t)).collect();
+
+  // samples only depend on the seed and the time, whatever the order
+  let mut other = Shake::new(7).with_amplitude(Cont::constant(0.5));
+
+  for (t, offset) in times.iter().zip(&offsets).rev() {
+    assert_eq!(other.at(*t), *offset);
+  }
+
+  assert!(offsets.iter().all(|&(tr, _)| tr.x.abs() <= 0.5 && tr.y.abs() <= 0.5 && tr.z.abs() <= 0.5));
+  assert!(offsets.windows(2).any(|w| w[0].0 != w[1].0));
+  assert!(Shake::new(8).with_amplitude(Cont::constant(0.5)).at(1.).0 != offsets[27].0);
+
+  // the frequency is integrated: slowing down after a while doesn’t jump back in the noise
+  let mut shake = Shake::new(7).with_frequency(Cont::new(|t| if t < 10. { 8. } else { 4. }));
+  let before = shake.at(10.).0;
+  let after = shake.at(10.001).0;
+  assert!(nalgebra::norm(&(after - before)) < 1e-2);
+
+  // hits
+  let mut shake = Shake::new(7).with_decay(Cont::constant(2.)).with_hits(vec![3., 1.]);
+  assert_eq!(shake.envelope(0.5), 0.);
+  assert_eq!(shake.at(0.5).0, Vector3::new(0., 0., 0.));
+  assert_eq!(shake.envelope(1.), 1.);
+  assert!((shake.envelope(2.) - (-2f32).exp()).abs() < 1e-6);
+  assert_eq!(shake.envelope(3.), 1.);
+
+  // offsets follow the axes of the view
+  let mut shake = Shake::new(3).with_rotation(Cont::constant(0.));
+  let view = Transform::default().reorient(Vector3::new(0., 1., 0.), 1.);
+  let (translation, _) = shake.at(2.);
+  let shaken = shake.apply(2., view);
+  let offset = view.orientation.rotate(&(shaken.translation - view.translation));
+
+  assert!(nalgebra::norm(&(offset - translation)) < 1e-5);
+}